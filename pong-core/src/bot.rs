@@ -0,0 +1,125 @@
+//! Lets programs play. How a bot connects is up to the server; once it has
+//! joined a game with `join` it is ready and is first sent `matched`:
+//!
+//! ```json
+//! {"event_type": "matched", "event_body": {"game_id": "…", "player_id": "p2",
+//!   "side": "right", "arena": {"width": 100, "height": 66.7},
+//!   "paddle_width": 1.3, "tick_deadline_ms": 15}}
+//! ```
+//!
+//! then every tick of the game, with all positions in arena units and
+//! velocities in arena units per second,
+//!
+//! ```json
+//! {"event_type": "observation", "event_body": {"tick": 42,
+//!   "balls": [{"id": 0, "x": 50, "y": 33, "vx": -40, "vy": 12}],
+//!   "paddles": {"p1": {"x": 0, "y": 29, "length": 8, "side": "left",
+//!     "score": 1, "lives": null}, "p2": {…}},
+//!   "clock": null}}
+//! ```
+//!
+//! A paddle's `x` and `y` are its top left corner. The bot answers with the
+//! position along its wall it wants the top (or left) of its paddle at:
+//!
+//! ```json
+//! {"event_type": "action", "event_body": {"tick": 42, "position": 30.5}}
+//! ```
+//!
+//! Actions that aren't for the latest tick, or arrive more than
+//! `TICK_DEADLINE` after it was sent, are ignored and answered with
+//! `action_late`. Bots also get the `ball_hit`, `end_point`, `ball_scored`,
+//! `game_over` and rematch messages human players get, in pixels, and can
+//! send `rematch_request` and `rematch_accept` like them. The server may also
+//! send `announcement`, `kicked`, `game_terminated` and `server_shutdown`
+//! notices, each with a `text` for people. After `server_shutdown` no new
+//! games are matched, and games and rematches aren't started. Games in
+//! progress are picked back up when the server restarts: reconnect to
+//! `/ws/{game_id}/{player_id}` and send `ready` again to carry on. All of
+//! these messages are typed in `protocol`.
+
+use std::{collections::HashMap, time::Duration};
+
+use uuid::Uuid;
+
+use crate::{
+    game::{Game, GameMode, GameStatus},
+    protocol::{Arena, Matched, ServerEvent},
+    settings::MatchSettings,
+};
+
+/// How long a bot has to answer an observation, the time between two ticks.
+pub const TICK_DEADLINE: Duration = Duration::from_millis(15);
+
+/// Adds a bot to `game_id`, or to any game waiting for bots if no game is
+/// given, opening a new one if there are none. Returns the game and player ids.
+pub fn join(
+    games: &mut HashMap<String, Game>,
+    game_id: Option<&str>,
+    player_id: Option<&str>,
+) -> Result<(String, String), String> {
+    let game_id = match game_id {
+        Some(game_id) => game_id.to_string(),
+        None => match games
+            .values()
+            .find(|g| g.open_to_bots && g.status == GameStatus::Waiting && !g.is_full())
+        {
+            Some(game) => game.id.clone(),
+            None => {
+                let id = Uuid::new_v4().to_string();
+                let mut game = Game::new(id.clone(), GameMode::Singles, MatchSettings::default());
+                game.open_to_bots = true;
+                games.insert(id.clone(), game);
+                id
+            }
+        },
+    };
+
+    let Some(game) = games.get_mut(&game_id) else {
+        return Err(format!("no such game: {}", game_id));
+    };
+    if game.is_full() || player_id.is_some_and(|p| !game.is_slot_free(p)) {
+        return Err(format!("game already full: {}", game_id));
+    }
+    let player_id = game.add_player_as(player_id).to_string();
+    game.set_player_bot(&player_id);
+    Ok((game_id, player_id))
+}
+
+/// The first message a bot gets, telling it where it is playing.
+pub fn matched(game: &Game, player_id: &str) -> ServerEvent {
+    ServerEvent::Matched(Matched {
+        game_id: game.id.clone(),
+        player_id: player_id.into(),
+        side: game.get_player(player_id).side,
+        arena: Arena {
+            width: game.settings.width,
+            height: game.settings.height,
+        },
+        paddle_width: game.settings.player_width,
+        tick_deadline_ms: TICK_DEADLINE.as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bots_fill_open_games_before_opening_new_ones() {
+        let mut games = HashMap::new();
+
+        let (first_game, first_player) = join(&mut games, None, None).unwrap();
+        let (second_game, second_player) = join(&mut games, None, None).unwrap();
+        assert_eq!(first_game, second_game);
+        assert_eq!(
+            (first_player.as_str(), second_player.as_str()),
+            ("p1", "p2")
+        );
+        assert!(games[&first_game].get_player("p2").bot);
+
+        let (third_game, _) = join(&mut games, None, None).unwrap();
+        assert_ne!(third_game, first_game);
+        assert!(join(&mut games, Some(&first_game), None).is_err());
+        assert!(join(&mut games, Some("missing"), None).is_err());
+    }
+}
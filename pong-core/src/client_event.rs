@@ -0,0 +1,126 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+#[allow(clippy::enum_variant_names)]
+pub enum ClientEventType {
+    MoveEvent(MoveEvent),
+    ReadyEvent,
+    RematchRequestEvent(RematchRequestEvent),
+    RematchAcceptEvent,
+    ActionEvent(ActionEvent),
+}
+
+/// Horizontal paddles move in x, vertical ones in y. Clients only need to
+/// send the coordinate their paddle can move in.
+#[derive(Deserialize)]
+pub struct MoveEvent {
+    #[serde(default)]
+    pub x: Option<f32>,
+    #[serde(default)]
+    pub y: Option<f32>,
+}
+
+/// A bot's reply to the observation for `tick`, moving its paddle to
+/// `position` along its wall in arena units.
+#[derive(Deserialize)]
+pub struct ActionEvent {
+    pub tick: u64,
+    pub position: f32,
+}
+
+#[derive(Deserialize)]
+pub struct RematchRequestEvent {
+    #[serde(default)]
+    pub swap_sides: bool,
+}
+
+impl<'de> Deserialize<'de> for ClientEventType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map: Map<String, Value> = Deserialize::deserialize(deserializer)?;
+
+        let event_type = map.get("event_type").and_then(|v| v.as_str());
+        // only needed by events that carry something
+        let event_body = || {
+            map.get("event_body")
+                .cloned()
+                .ok_or_else(|| serde::de::Error::missing_field("event_body"))
+        };
+
+        match event_type {
+            Some("ready") => Ok(ClientEventType::ReadyEvent),
+            Some("move") => {
+                let e = serde_json::from_value(event_body()?).map_err(serde::de::Error::custom)?;
+                Ok(ClientEventType::MoveEvent(e))
+            }
+            Some("rematch_request") => {
+                let e = serde_json::from_value(event_body()?).map_err(serde::de::Error::custom)?;
+                Ok(ClientEventType::RematchRequestEvent(e))
+            }
+            Some("rematch_accept") => Ok(ClientEventType::RematchAcceptEvent),
+            Some("action") => {
+                let e = serde_json::from_value(event_body()?).map_err(serde::de::Error::custom)?;
+                Ok(ClientEventType::ActionEvent(e))
+            }
+            _ => Err(serde::de::Error::custom("Unknown event type")),
+        }
+    }
+}
+
+pub fn parse_client_event(json_data: &str) -> Option<ClientEventType> {
+    match serde_json::from_str(json_data) {
+        Ok(event_type) => Some(event_type),
+        Err(err) => {
+            log::error!("Failed to parse json: {}. Err: {}", json_data, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_ready_event() {
+        let json_data = r#"
+            {
+                "event_type": "ready",
+                "event_body": {}
+            }
+        "#;
+
+        let result = match parse_client_event(json_data) {
+            Some(ClientEventType::ReadyEvent) => true,
+            _ => panic!("Event type not recognised"),
+        };
+
+        assert!(result);
+    }
+
+    #[test]
+    fn can_parse_rematch_request_event() {
+        let json_data = r#"
+            {
+                "event_type": "rematch_request",
+                "event_body": { "swap_sides": true }
+            }
+        "#;
+
+        match parse_client_event(json_data) {
+            Some(ClientEventType::RematchRequestEvent(e)) => assert!(e.swap_sides),
+            _ => panic!("Event type not recognised"),
+        };
+    }
+
+    #[test]
+    fn events_without_a_body_are_rejected_only_if_they_need_one() {
+        assert!(parse_client_event(r#"{ "event_type": "move" }"#).is_none());
+        assert!(matches!(
+            parse_client_event(r#"{ "event_type": "ready" }"#),
+            Some(ClientEventType::ReadyEvent)
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+//! A headless singles match for training and evaluating AI opponents offline,
+//! in the style of a gym environment. It steps the physics as fast as it can
+//! with no clock, server or async runtime, and every random choice comes from
+//! the seed given to `reset`, so the same seed and actions always play out the
+//! same.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    game::POINTS_TO_WIN,
+    pong::{Lane, Orientation, Pong, Side},
+    settings::{BallSettings, MatchSettings},
+};
+
+/// Arena units a paddle moves in a step at full speed, about what the browser
+/// client moves it per frame.
+pub const PADDLE_STEP: f32 = 1.0;
+
+/// Normalised to the arena: ball x and y, ball velocity in arena widths per
+/// second, then the centres of the left and right paddles.
+pub type Observation = [f32; 6];
+
+/// How far each paddle, left then right, moves this step, from -1 (full
+/// speed up) to 1 (full speed down).
+pub type Action = [f32; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub observation: Observation,
+    /// For the left and right player: 1 for scoring a point, -1 for
+    /// conceding one.
+    pub reward: [f32; 2],
+    /// The match has been won or has gone on for `max_steps`.
+    pub done: bool,
+}
+
+pub struct Environment {
+    settings: MatchSettings,
+    max_steps: u32,
+    rng: StdRng,
+    pong: Pong,
+    /// Top left corners of the left and right paddles.
+    paddles: [(f32, f32); 2],
+    /// Position and velocity of the ball.
+    ball: [f32; 4],
+    scores: [u32; 2],
+    steps: u32,
+}
+
+impl Environment {
+    /// A match with `settings`, always with a single ball, cut short after
+    /// `max_steps` in case neither side can get one past the other.
+    pub fn new(settings: MatchSettings, max_steps: u32) -> Self {
+        let settings = MatchSettings {
+            balls: BallSettings {
+                count: 0,
+                spawn_every: None,
+            },
+            ..settings
+        };
+        let rng = StdRng::seed_from_u64(0);
+        let pong = Pong::new(6.0, &[], None, &settings, 0);
+        let mut environment = Environment {
+            settings,
+            max_steps,
+            rng,
+            pong,
+            paddles: [(0.0, 0.0); 2],
+            ball: [0.0; 4],
+            scores: [0, 0],
+            steps: 0,
+        };
+        environment.reset(0);
+        environment
+    }
+
+    /// Starts a new match and returns the first observation.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.scores = [0, 0];
+        self.steps = 0;
+        self.new_point();
+        self.observation()
+    }
+
+    /// Moves both paddles and advances the ball by one physics step.
+    pub fn step(&mut self, action: Action) -> Step {
+        let length = self.settings.player_height;
+        for (paddle, action) in self.paddles.iter_mut().zip(action) {
+            paddle.1 = Lane::Full.clamp(
+                Orientation::Vertical,
+                length,
+                paddle.1 + action.clamp(-1.0, 1.0) * PADDLE_STEP,
+                &self.settings,
+            );
+        }
+
+        let balls = self.pong.next(Some(&self.paddles));
+        self.steps += 1;
+
+        let mut reward = [0.0; 2];
+        if let Some(ball) = balls.first() {
+            self.ball = [ball.x, ball.y, ball.vx, ball.vy];
+            let scorer = match ball.wall_contact {
+                Some(Side::Left) => Some(1),
+                Some(Side::Right) => Some(0),
+                _ => None,
+            };
+            if let Some(scorer) = scorer {
+                self.scores[scorer] += 1;
+                reward[scorer] = 1.0;
+                reward[1 - scorer] = -1.0;
+                self.new_point();
+            }
+        }
+
+        Step {
+            observation: self.observation(),
+            reward,
+            done: self.winner().is_some() || self.steps >= self.max_steps,
+        }
+    }
+
+    pub fn observation(&self) -> Observation {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let half_length = self.settings.player_height / 2.0;
+        let [x, y, vx, vy] = self.ball;
+        [
+            x / width,
+            y / height,
+            vx / width,
+            vy / width,
+            (self.paddles[0].1 + half_length) / height,
+            (self.paddles[1].1 + half_length) / height,
+        ]
+    }
+
+    pub fn scores(&self) -> [u32; 2] {
+        self.scores
+    }
+
+    /// 0 for the left player, 1 for the right, once either has won.
+    pub fn winner(&self) -> Option<usize> {
+        self.scores.iter().position(|s| *s >= POINTS_TO_WIN)
+    }
+
+    /// Puts the paddles back and serves a new ball from the centre.
+    fn new_point(&mut self) {
+        let length = self.settings.player_height;
+        self.paddles =
+            [Side::Left, Side::Right].map(|side| side.start(Lane::Full, length, &self.settings));
+        let paddles = self
+            .paddles
+            .map(|(x, y)| (x, y, Orientation::Vertical, length));
+        self.pong = Pong::new(6.0, &paddles, None, &self.settings, 0);
+        self.pong.serve_ball_with(&mut self.rng, None);
+
+        let ball = &self.pong.next(Some(&self.paddles))[0];
+        self.ball = [ball.x, ball.y, ball.vx, ball.vy];
+    }
+}
+
+/// Follows the ball with the paddle on `side`, a baseline to train and
+/// evaluate against.
+pub fn track_ball(observation: &Observation, side: Side) -> f32 {
+    let paddle = if side == Side::Left {
+        observation[4]
+    } else {
+        observation[5]
+    };
+    ((observation[1] - paddle) * 20.0).clamp(-1.0, 1.0)
+}
+
+/// How the baseline got on in `evaluate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    pub episodes: u64,
+    /// Matches the baseline won.
+    pub wins: u64,
+    /// Points scored by the baseline and by its opponent.
+    pub points: [u32; 2],
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "baseline won {} of {} matches, {}-{} on points, {} steps at {:.0} steps/s",
+            self.wins,
+            self.episodes,
+            self.points[0],
+            self.points[1],
+            self.steps,
+            self.steps as f64 / self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Plays `episodes` seeded matches of the baseline on the left against a
+/// paddle moving at random on the right.
+pub fn evaluate(episodes: u64) -> Evaluation {
+    let mut environment = Environment::new(MatchSettings::default(), 100_000);
+    let mut random = StdRng::seed_from_u64(0);
+    let (mut wins, mut points, mut steps) = (0, [0, 0], 0);
+    let started = Instant::now();
+
+    for seed in 0..episodes {
+        let mut observation = environment.reset(seed);
+        loop {
+            let action = [
+                track_ball(&observation, Side::Left),
+                random.gen_range(-1.0..=1.0),
+            ];
+            let step = environment.step(action);
+            steps += 1;
+            observation = step.observation;
+            if step.done {
+                break;
+            }
+        }
+        if environment.winner() == Some(0) {
+            wins += 1;
+        }
+        let scores = environment.scores();
+        points = [points[0] + scores[0], points[1] + scores[1]];
+    }
+
+    Evaluation {
+        episodes,
+        wins,
+        points,
+        steps,
+        elapsed: started.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(environment: &mut Environment, seed: u64) -> (Vec<Observation>, [u32; 2]) {
+        let mut observation = environment.reset(seed);
+        let mut observations = vec![observation];
+        loop {
+            let step = environment.step([
+                track_ball(&observation, Side::Left),
+                track_ball(&observation, Side::Right) * 0.3,
+            ]);
+            observation = step.observation;
+            observations.push(observation);
+            if step.done {
+                return (observations, environment.scores());
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        let mut environment = Environment::new(MatchSettings::default(), 20_000);
+
+        let (first, scores) = play(&mut environment, 7);
+        let (second, _) = play(&mut environment, 7);
+        let (other, _) = play(&mut environment, 8);
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(environment.winner(), Some(0));
+        assert_eq!(scores[0], POINTS_TO_WIN);
+    }
+
+    #[test]
+    fn evaluation_counts_every_match() {
+        let evaluation = evaluate(2);
+        assert_eq!(evaluation.episodes, 2);
+        assert!(evaluation.wins <= 2);
+        assert!(evaluation.points.iter().any(|p| *p >= POINTS_TO_WIN));
+        assert!(evaluation.steps > 0);
+    }
+}
@@ -0,0 +1,1481 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::TICK_DEADLINE,
+    player::Player,
+    pong::{BallSnapshot, BallState, Lane, Orientation, Pong, Side},
+    power_up::{ActiveEffect, PowerUp, PowerUps, POWER_UP_RADIUS},
+    practice::PracticeStats,
+    protocol::{
+        ActionLate, BallHit, BallPosition, BallScored, GameOver, Observation, ObservedBall,
+        ObservedPaddle, ObstaclePosition, Position, PowerUpCollected, PowerUpEnded, PowerUpExpired,
+        PowerUpSpawned, RematchRequested, RematchStarted, Scores, ServerEvent, StatsSummary,
+        UpdateScreen,
+    },
+    settings::{MatchSettings, MAX_BALLS},
+    stats::MatchStats,
+};
+
+pub const PLAYER_1: &str = "p1";
+pub const PLAYER_2: &str = "p2";
+pub const PLAYER_3: &str = "p3";
+pub const PLAYER_4: &str = "p4";
+pub const POINTS_TO_WIN: u32 = 5;
+pub const START_LIVES: u32 = 3;
+
+/// A message for the players in `to`. Games queue these up for whoever is
+/// connecting them to players to send on, see `Game::take_outbox`.
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub to: Vec<String>,
+    pub message: ServerEvent,
+}
+
+struct StepResult {
+    balls: Vec<BallState>,
+    obstacles: Vec<(usize, f32, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    Singles,
+    Doubles,
+    FreeForAll,
+    /// One player against a wall or a ball machine, for drilling returns.
+    Practice,
+}
+
+impl GameMode {
+    /// The player slots for this mode, in the order they are filled. In team
+    /// modes `PLAYER_1` and `PLAYER_3` are always on one team, `PLAYER_2` and
+    /// `PLAYER_4` on the other.
+    pub fn slots(&self) -> &'static [(&'static str, Side, Lane)] {
+        match self {
+            GameMode::Singles => &[
+                (PLAYER_1, Side::Left, Lane::Full),
+                (PLAYER_2, Side::Right, Lane::Full),
+            ],
+            GameMode::Doubles => &[
+                (PLAYER_1, Side::Left, Lane::Top),
+                (PLAYER_2, Side::Right, Lane::Top),
+                (PLAYER_3, Side::Left, Lane::Bottom),
+                (PLAYER_4, Side::Right, Lane::Bottom),
+            ],
+            GameMode::FreeForAll => &[
+                (PLAYER_1, Side::Left, Lane::Full),
+                (PLAYER_2, Side::Right, Lane::Full),
+                (PLAYER_3, Side::Top, Lane::Full),
+                (PLAYER_4, Side::Bottom, Lane::Full),
+            ],
+            GameMode::Practice => &[(PLAYER_1, Side::Left, Lane::Full)],
+        }
+    }
+
+    /// Free-for-all players each guard their own wall and are knocked out once
+    /// it has been hit `START_LIVES` times. Team modes play to `POINTS_TO_WIN`.
+    pub fn starting_lives(&self) -> Option<u32> {
+        match self {
+            GameMode::FreeForAll => Some(START_LIVES),
+            _ => None,
+        }
+    }
+
+    pub fn max_players(&self) -> usize {
+        self.slots().len()
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "singles" => Ok(GameMode::Singles),
+            "doubles" => Ok(GameMode::Doubles),
+            "free_for_all" => Ok(GameMode::FreeForAll),
+            "practice" => Ok(GameMode::Practice),
+            _ => Err(format!("unknown game mode: {}", s)),
+        }
+    }
+}
+
+/// A tournament or league a game is being played in, by id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Competition {
+    Tournament(String),
+    League(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Waiting,
+    InProgress,
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub id: String,
+    pub mode: GameMode,
+    pub settings: MatchSettings,
+    pub status: GameStatus,
+    /// What is left on the match clock in timed matches. Once it reaches zero
+    /// the leader wins, or the next point decides it if there isn't one.
+    pub time_left: Option<Duration>,
+    /// Results of competition games are reported back to it and there are
+    /// no rematches.
+    pub competition: Option<Competition>,
+    pub practice: PracticeStats,
+    pub stats: MatchStats,
+    /// Bots looking for a match join games open to them.
+    pub open_to_bots: bool,
+    pub created_at: Instant,
+    /// When a player last joined or sent anything, for clearing out games
+    /// that have been left.
+    pub last_active: Instant,
+    players: HashMap<String, Player>,
+    swap_sides_on_rematch: bool,
+    /// Number of the latest observation sent to bots, and when it was sent.
+    tick: u64,
+    tick_sent: Option<Instant>,
+    outbox: Vec<Outgoing>,
+    /// Balls in play as of the last tick, kept for `Game::snapshot`.
+    balls: Vec<BallSnapshot>,
+    holding_serve: bool,
+    /// Every point's arena is seeded from this and the points played before
+    /// it, so a match picked up between points serves the same balls.
+    seed: u64,
+}
+
+/// Everything needed to carry on with a match somewhere else, like a server
+/// that has been restarted, see `Game::snapshot` and `Game::restore`. Moving
+/// obstacles and power-ups start over, and one picked up mid-rally only keeps
+/// the balls in play, the next point is served as it would have been.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub id: String,
+    pub mode: GameMode,
+    pub settings: MatchSettings,
+    pub time_left: Option<Duration>,
+    pub competition: Option<Competition>,
+    pub practice: PracticeStats,
+    pub stats: MatchStats,
+    pub open_to_bots: bool,
+    pub players: HashMap<String, Player>,
+    pub balls: Vec<BallSnapshot>,
+    pub seed: u64,
+}
+
+impl Game {
+    pub fn new(id: String, mode: GameMode, settings: MatchSettings) -> Self {
+        Game {
+            id,
+            mode,
+            time_left: settings.time_limit,
+            settings,
+            competition: None,
+            practice: PracticeStats::default(),
+            stats: MatchStats::default(),
+            open_to_bots: false,
+            created_at: Instant::now(),
+            last_active: Instant::now(),
+            status: GameStatus::Waiting,
+            players: HashMap::new(),
+            swap_sides_on_rematch: false,
+            tick: 0,
+            tick_sent: None,
+            outbox: vec![],
+            balls: vec![],
+            holding_serve: false,
+            seed: rand::random(),
+        }
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            id: self.id.clone(),
+            mode: self.mode,
+            settings: self.settings.clone(),
+            time_left: self.time_left,
+            competition: self.competition.clone(),
+            practice: self.practice,
+            stats: self.stats.clone(),
+            open_to_bots: self.open_to_bots,
+            players: self.players.clone(),
+            balls: self.balls.clone(),
+            seed: self.seed,
+        }
+    }
+
+    /// Rebuilds a game from a snapshot. It waits for its players to ready up
+    /// again, then an `Engine` carries the match on from where it was.
+    pub fn restore(snapshot: GameSnapshot) -> Self {
+        let mut game = Game::new(snapshot.id, snapshot.mode, snapshot.settings);
+        game.time_left = snapshot.time_left;
+        game.competition = snapshot.competition;
+        game.practice = snapshot.practice;
+        game.stats = snapshot.stats;
+        game.open_to_bots = snapshot.open_to_bots;
+        game.players = snapshot.players;
+        game.balls = snapshot.balls;
+        game.seed = snapshot.seed;
+        for player in game.players.values_mut() {
+            // knocked out players have nothing to come back for
+            player.is_ready = player.is_eliminated();
+            player.wants_rematch = false;
+        }
+        game
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.num_players() >= self.mode.max_players()
+    }
+
+    pub fn is_slot_free(&self, player_id: &str) -> bool {
+        self.mode.slots().iter().any(|(id, _, _)| *id == player_id)
+            && !self.players.contains_key(player_id)
+    }
+
+    pub fn add_player(&mut self) -> &str {
+        self.add_player_as(None)
+    }
+
+    /// Adds a player in the slot `player_id` if it is still free, or in the
+    /// first free slot if no particular one is wanted.
+    pub fn add_player_as(&mut self, player_id: Option<&str>) -> &str {
+        self.last_active = Instant::now();
+        if let Some((id, side, lane)) = self.mode.slots().iter().find(|(id, _, _)| {
+            !self.players.contains_key(*id) && (player_id.is_none() || player_id == Some(*id))
+        }) {
+            self.players.insert(
+                String::from(*id),
+                Player::new(
+                    *side,
+                    *lane,
+                    self.mode.starting_lives(),
+                    self.settings.handicap(id),
+                    &self.settings,
+                ),
+            );
+            id
+        } else {
+            panic!("Too many players")
+        }
+    }
+
+    pub fn set_player_ready(&mut self, player_id: &str) {
+        self.last_active = Instant::now();
+        if let Some(mut player) = self.players.get(player_id).cloned() {
+            player.is_ready = true;
+            self.players.insert(player_id.into(), player);
+        }
+    }
+
+    pub fn set_player_bot(&mut self, player_id: &str) {
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.bot = true;
+        }
+    }
+
+    /// Moves a bot's paddle to `position` along its wall in reply to the
+    /// observation for `tick`. Returns false, counting it against the bot, if
+    /// that isn't the latest observation or the reply missed `TICK_DEADLINE`.
+    pub fn act(&mut self, player_id: &str, tick: u64, position: f32) -> bool {
+        self.last_active = Instant::now();
+        let on_time = tick == self.tick
+            && self
+                .tick_sent
+                .is_some_and(|sent| sent.elapsed() <= TICK_DEADLINE);
+        let Some(player) = self.players.get_mut(player_id) else {
+            return false;
+        };
+        if !on_time {
+            player.late_actions += 1;
+            send_action_late(self, player_id, tick);
+            return false;
+        }
+        match player.orientation {
+            Orientation::Vertical => player.move_to(None, Some(position), &self.settings),
+            Orientation::Horizontal => player.move_to(Some(position), None, &self.settings),
+        }
+        true
+    }
+
+    /// Moves a human player's paddle. Bots only move through `act`, so that
+    /// they are held to `TICK_DEADLINE`.
+    pub fn update_player_pos(&mut self, player_id: &str, x: Option<f32>, y: Option<f32>) {
+        self.last_active = Instant::now();
+        if let Some(mut player) = self.players.get(player_id).cloned() {
+            if player.bot {
+                log::debug!("ignoring move from bot {}", player_id);
+                return;
+            }
+            player.move_to(x, y, &self.settings);
+            log::trace!("{} pos: {},{}", player_id, player.x, player.y);
+            self.players.insert(player_id.into(), player);
+        }
+    }
+
+    pub fn both_players_ready(&self) -> bool {
+        self.is_full() && self.players.values().all(|p| p.is_ready)
+    }
+
+    /// Takes `player_id` out of a game that hasn't started, freeing their slot.
+    /// Returns false if they weren't in it or it's too late to leave.
+    pub fn remove_player(&mut self, player_id: &str) -> bool {
+        self.status == GameStatus::Waiting && self.players.remove(player_id).is_some()
+    }
+
+    /// Keeps `player_id` out of a game that has started, see `is_kicked`.
+    /// Returns false if they aren't in it.
+    pub fn kick_player(&mut self, player_id: &str) -> bool {
+        match self.players.get_mut(player_id) {
+            Some(player) => {
+                player.kicked = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_kicked(&self, player_id: &str) -> bool {
+        self.players.get(player_id).is_some_and(|p| p.kicked)
+    }
+
+    /// Every player with their id, in slot order.
+    pub fn players(&self) -> Vec<(&str, &Player)> {
+        let mut players: Vec<_> = self
+            .players
+            .iter()
+            .map(|(id, p)| (id.as_str(), p))
+            .collect();
+        players.sort_by_key(|(id, _)| *id);
+        players
+    }
+
+    pub fn get_player(&self, player_id: &str) -> &Player {
+        if let Some(player) = self.players.get(player_id) {
+            return player;
+        }
+        panic!("no such player")
+    }
+
+    /// Ids of the players still in the game in a fixed order, matching the
+    /// paddles of the `Pong` built by `new_pong`.
+    fn paddle_order(&self) -> Vec<&String> {
+        let mut ids: Vec<&String> = self
+            .players
+            .iter()
+            .filter(|(_, p)| !p.is_eliminated())
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn paddle_player(&self, paddle: usize) -> Option<&str> {
+        self.paddle_order().get(paddle).map(|id| id.as_str())
+    }
+
+    fn paddle_positions(&self) -> Vec<(f32, f32)> {
+        self.paddle_order()
+            .into_iter()
+            .map(|id| (self.players[id].x, self.players[id].y))
+            .collect()
+    }
+
+    fn new_pong(&self) -> Pong {
+        let paddles: Vec<_> = self
+            .paddle_order()
+            .into_iter()
+            .map(|id| {
+                let p = &self.players[id];
+                (p.x, p.y, p.orientation, p.paddle_length(&self.settings))
+            })
+            .collect();
+        let serve_to = match self.mode {
+            GameMode::Practice => Some(Side::Left),
+            _ => self
+                .paddle_order()
+                .into_iter()
+                .map(|id| &self.players[id])
+                .find(|p| p.handicap.serves)
+                .map(|p| p.side.opposite()),
+        };
+        let seed = self.seed.wrapping_add(u64::from(self.stats.points));
+        Pong::new(6.0, &paddles, serve_to, &self.settings, seed)
+    }
+
+    fn score_point(&mut self, side: Side) {
+        for player in self.players.values_mut() {
+            if player.side == side {
+                player.score += 1;
+            }
+        }
+    }
+
+    fn reset_positions(&mut self) {
+        for player in self.players.values_mut() {
+            player.reset_position(&self.settings);
+        }
+    }
+
+    /// Handles a ball hitting the wall on `side`. Returns true if that scored.
+    fn concede(&mut self, side: Side) -> bool {
+        match self.mode {
+            GameMode::FreeForAll => {
+                // an eliminated player's wall is just a wall
+                let Some(player) = self
+                    .players
+                    .values_mut()
+                    .find(|p| p.side == side && !p.is_eliminated())
+                else {
+                    return false;
+                };
+                player.lives = player.lives.map(|lives| lives.saturating_sub(1));
+                true
+            }
+            GameMode::Singles | GameMode::Doubles => match side {
+                Side::Left | Side::Right => {
+                    self.score_point(side.opposite());
+                    true
+                }
+                Side::Top | Side::Bottom => false,
+            },
+            GameMode::Practice => {
+                if side == Side::Left {
+                    self.practice.record_miss();
+                }
+                side == Side::Left
+            }
+        }
+    }
+
+    /// Counts the match clock down to `elapsed` since kick off. Returns true
+    /// on the tick time runs out.
+    pub fn tick_clock(&mut self, elapsed: Duration) -> bool {
+        let Some(limit) = self.settings.time_limit else {
+            return false;
+        };
+        let was_running = !self.is_time_up();
+        self.time_left = Some(limit.saturating_sub(elapsed));
+        was_running && self.is_time_up()
+    }
+
+    pub fn is_time_up(&self) -> bool {
+        self.time_left == Some(Duration::ZERO)
+    }
+
+    /// The side strictly ahead on points, or on lives in free-for-all.
+    fn leading_side(&self) -> Option<Side> {
+        let mut standings: Vec<(Side, u32)> = self
+            .players
+            .values()
+            .filter(|p| !p.is_eliminated())
+            .map(|p| (p.side, p.lives.unwrap_or(p.score)))
+            .collect();
+        standings.sort_by_key(|(_, standing)| std::cmp::Reverse(*standing));
+        standings.dedup_by_key(|(side, _)| *side);
+        match standings.as_slice() {
+            [(side, _)] => Some(*side),
+            [(side, first), (_, second), ..] if first > second => Some(*side),
+            _ => None,
+        }
+    }
+
+    pub fn winning_side(&self) -> Option<Side> {
+        match self.mode {
+            GameMode::FreeForAll => {
+                let remaining: Vec<&Player> = self
+                    .players
+                    .values()
+                    .filter(|p| !p.is_eliminated())
+                    .collect();
+                match remaining.as_slice() {
+                    [last] if self.is_full() => Some(last.side),
+                    _ if self.is_time_up() => self.leading_side(),
+                    _ => None,
+                }
+            }
+            GameMode::Singles | GameMode::Doubles if self.settings.time_limit.is_some() => {
+                if self.is_time_up() {
+                    self.leading_side()
+                } else {
+                    None
+                }
+            }
+            GameMode::Singles | GameMode::Doubles => self
+                .players
+                .values()
+                .find(|p| p.score >= POINTS_TO_WIN)
+                .map(|p| p.side),
+            GameMode::Practice => None,
+        }
+    }
+
+    pub fn winners(&self) -> Vec<&str> {
+        match self.winning_side() {
+            Some(side) => self
+                .players
+                .iter()
+                .filter(|(_, p)| p.side == side)
+                .map(|(id, _)| id.as_str())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Marks `player_id` as wanting a rematch and lets the other player know.
+    /// Returns false if the match is still going.
+    pub fn request_rematch(&mut self, player_id: &str, swap_sides: bool) -> bool {
+        self.last_active = Instant::now();
+        if self.status != GameStatus::Finished || self.competition.is_some() {
+            return false;
+        }
+
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.wants_rematch = true;
+            self.swap_sides_on_rematch = swap_sides;
+            send_rematch_requested(self, player_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Has `player_id` agree to a rematch someone else has asked for, and
+    /// resets the finished match once every player wants one. Returns true if
+    /// the rematch should be started.
+    pub fn accept_rematch(&mut self, player_id: &str) -> bool {
+        self.last_active = Instant::now();
+        if self.status != GameStatus::Finished
+            || !self
+                .players
+                .iter()
+                .any(|(id, p)| id != player_id && p.wants_rematch)
+        {
+            return false;
+        }
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.wants_rematch = true;
+        }
+        if !self.players.values().all(|p| p.wants_rematch) {
+            return false;
+        }
+
+        let swap_sides = self.swap_sides_on_rematch;
+        for player in self.players.values_mut() {
+            if swap_sides {
+                player.side = player.side.opposite();
+            }
+            player.reset_score(self.mode.starting_lives());
+            player.is_ready = true;
+            player.wants_rematch = false;
+            player.reset_position(&self.settings);
+        }
+        self.swap_sides_on_rematch = false;
+        self.status = GameStatus::Waiting;
+        self.time_left = self.settings.time_limit;
+        self.stats = MatchStats::default();
+        self.balls.clear();
+        self.seed = rand::random();
+
+        send_rematch_started(self, swap_sides);
+        true
+    }
+
+    fn step(&self, pong: &mut Pong) -> StepResult {
+        StepResult {
+            balls: pong.next(Some(&self.paddle_positions())),
+            obstacles: pong.moving_obstacle_positions(),
+        }
+    }
+
+    /// Puts the match in progress. Returns false if it already was or is over,
+    /// finished matches only start again through `accept_rematch`. Otherwise
+    /// it is up to the caller to play it with an `Engine`.
+    pub fn start(&mut self) -> bool {
+        if self.status != GameStatus::Waiting {
+            return false;
+        }
+        self.status = GameStatus::InProgress;
+        true
+    }
+
+    /// Lets the point being played finish but doesn't serve the next one,
+    /// leaving the match paused between points for good.
+    pub fn hold_serve(&mut self) {
+        self.holding_serve = true;
+    }
+
+    /// Messages queued for players since the last call, oldest first.
+    pub fn take_outbox(&mut self) -> Vec<Outgoing> {
+        std::mem::take(&mut self.outbox)
+    }
+}
+
+/// Plays a game's match tick by tick: moves the ball, scores points and
+/// hands out power-ups.
+pub struct Engine {
+    pong: Pong,
+    kick_off: Instant,
+    last_spawn: Instant,
+    power_ups: PowerUps,
+}
+
+impl Engine {
+    /// Kicks off `game`'s match, or picks it back up if it is a restored one
+    /// that had already been going.
+    pub fn new(game: &Game) -> Self {
+        let mut pong = game.new_pong();
+        if game.stats.time_in_play > Duration::ZERO && !game.balls.is_empty() {
+            pong.restore_balls(&game.balls);
+        }
+        Engine {
+            pong,
+            kick_off: Instant::now()
+                .checked_sub(game.stats.time_in_play)
+                .unwrap_or_else(Instant::now),
+            last_spawn: Instant::now(),
+            power_ups: PowerUps::new(),
+        }
+    }
+
+    /// Plays one tick of `game`. Returns true once the match is over.
+    pub fn tick(&mut self, game: &mut Game) -> bool {
+        if game.holding_serve && self.pong.num_balls() == 0 {
+            return false;
+        }
+        let time_ran_out = game.tick_clock(self.kick_off.elapsed());
+        let step_result = game.step(&mut self.pong);
+
+        send_update_screen(game, &step_result);
+        game.tick += 1;
+        game.tick_sent = Some(Instant::now());
+        send_observation(game, &step_result);
+        send_ball_hits(game, &step_result);
+        update_stats(game, &step_result, self.kick_off.elapsed());
+
+        if game.settings.power_ups {
+            update_power_ups(game, &mut self.pong, &mut self.power_ups, &step_result);
+        }
+
+        if game.mode == GameMode::Practice {
+            update_practice(game, &mut self.pong, &step_result);
+        }
+
+        let mut scored = vec![];
+        for ball in &step_result.balls {
+            if ball
+                .wall_contact
+                .is_some_and(|side| !self.power_ups.is_shielded(side) && game.concede(side))
+            {
+                self.pong.remove_ball(ball.id);
+                scored.push(ball.id);
+                end_rally(game, ball);
+            }
+        }
+
+        // a tie when time runs out goes to sudden death, the next point wins
+        if (!scored.is_empty() || time_ran_out) && game.winning_side().is_some() {
+            // running out of time ends the match but not the point
+            if !scored.is_empty() {
+                game.stats.points += 1;
+                send_end_point(game);
+            }
+            game.status = GameStatus::Finished;
+            for player in game.players.values_mut() {
+                player.is_ready = false;
+            }
+            send_game_over(game);
+            return true;
+        }
+
+        if !scored.is_empty() {
+            // knocked out players' paddles only leave the arena with a new point
+            if self.pong.num_balls() == 0 || self.pong.num_paddles() != game.paddle_order().len() {
+                game.reset_positions();
+                game.stats.forget_paddles();
+                game.stats.points += 1;
+                send_end_point(game);
+                self.pong = game.new_pong();
+                if game.holding_serve {
+                    self.pong.remove_balls();
+                }
+                self.last_spawn = Instant::now();
+                self.power_ups = PowerUps::new();
+            } else {
+                send_ball_scored(game, &scored);
+            }
+        }
+
+        if let Some(interval) = game.settings.balls.spawn_every {
+            if self.last_spawn.elapsed() >= interval {
+                if self.pong.num_balls() < MAX_BALLS {
+                    self.pong.spawn_ball();
+                }
+                self.last_spawn = Instant::now();
+            }
+        }
+
+        game.balls = self.pong.ball_snapshots();
+        false
+    }
+}
+
+/// Adds a step of play to the match's statistics.
+fn update_stats(game: &mut Game, step_result: &StepResult, time_in_play: Duration) {
+    game.stats.time_in_play = time_in_play;
+    for ball in &step_result.balls {
+        game.stats.record_speed(ball.vx.hypot(ball.vy));
+        let hit_by = ball
+            .paddle_hit
+            .and_then(|paddle| game.paddle_player(paddle))
+            .map(String::from);
+        if let Some(player_id) = hit_by {
+            game.stats.record_hit(&player_id, ball.rally);
+        }
+    }
+    for (id, player) in &game.players {
+        if !player.is_eliminated() {
+            game.stats.move_paddle(id, player.x, player.y);
+        }
+    }
+}
+
+/// Counts a scored ball's rally. One that went in straight from the serve is
+/// a point on serve for the side opposite the wall it went in at.
+fn end_rally(game: &mut Game, ball: &BallState) {
+    game.stats.end_rally(ball.rally);
+    let Some(conceded) = ball.wall_contact.filter(|_| ball.rally == 0) else {
+        return;
+    };
+    for (id, player) in &game.players {
+        if player.side == conceded.opposite() {
+            game.stats.record_point_on_serve(id);
+        }
+    }
+}
+
+/// Counts the practising player's returns and has the ball machine, if there
+/// is one, answer them. Misses are counted by `concede`.
+fn update_practice(game: &mut Game, pong: &mut Pong, step_result: &StepResult) {
+    let before = game.practice;
+    for ball in &step_result.balls {
+        if ball.paddle_hit.is_some() {
+            game.practice.record_return();
+        }
+        if let (Some(machine), Some(Side::Right)) = (game.settings.ball_machine, ball.wall_contact)
+        {
+            pong.remove_ball(ball.id);
+            machine.fire(pong);
+        }
+    }
+    if game.practice != before {
+        send_practice_stats(game);
+    }
+}
+
+fn update_power_ups(
+    game: &mut Game,
+    pong: &mut Pong,
+    power_ups: &mut PowerUps,
+    step_result: &StepResult,
+) {
+    for ball in &step_result.balls {
+        let (Some(id), Some(paddle)) = (ball.sensor_contact, ball.last_touched) else {
+            continue;
+        };
+        let Some(player_id) = game.paddle_player(paddle).map(String::from) else {
+            continue;
+        };
+        let side = game.get_player(&player_id).side;
+        if let Some(effect) = power_ups.collect(pong, id, paddle, ball.id, side) {
+            send_power_up_collected(game, id, &effect, &player_id, power_ups);
+        }
+    }
+
+    for power_up in power_ups.expire_uncollected(pong) {
+        send_power_up_expired(game, &power_up);
+    }
+
+    for effect in power_ups.expire_effects(pong) {
+        if let Some(player_id) = game.paddle_player(effect.paddle).map(String::from) {
+            send_power_up_ended(game, &effect, &player_id, power_ups);
+        }
+    }
+
+    if let Some(power_up) = power_ups.spawn_due(pong) {
+        send_power_up_spawned(game, &power_up);
+    }
+}
+
+fn send_end_point(game: &mut Game) {
+    let message = ServerEvent::EndPoint(scores(game));
+    broadcast(game, message);
+}
+
+/// Lets clients play a sound or show an effect whenever a ball bounces off a
+/// paddle or a wall.
+fn send_ball_hits(game: &mut Game, step_result: &StepResult) {
+    let scale = game.settings.scaling_factor;
+    for ball in &step_result.balls {
+        if ball.paddle_hit.is_none() && ball.wall_hit.is_none() {
+            continue;
+        }
+        let message = ServerEvent::BallHit(BallHit {
+            ball_id: ball.id,
+            player_id: ball
+                .paddle_hit
+                .and_then(|paddle| game.paddle_player(paddle))
+                .map(String::from),
+            wall: ball.wall_hit,
+            offset: ball.hit_offset,
+            rally: ball.rally,
+            speed: ball.vx.hypot(ball.vy) * scale,
+        });
+        broadcast(game, message);
+    }
+}
+
+fn send_ball_scored(game: &mut Game, ball_ids: &[u32]) {
+    let message = ServerEvent::BallScored(BallScored {
+        ball_ids: ball_ids.to_vec(),
+        scores: scores(game),
+    });
+    broadcast(game, message);
+}
+
+fn send_practice_stats(game: &mut Game) {
+    let message = ServerEvent::PracticeStats(game.practice);
+    broadcast(game, message);
+}
+
+fn send_power_up_spawned(game: &mut Game, power_up: &PowerUp) {
+    let scale = game.settings.scaling_factor;
+    let message = ServerEvent::PowerUpSpawned(PowerUpSpawned {
+        id: power_up.id,
+        kind: power_up.kind,
+        x: power_up.x * scale,
+        y: power_up.y * scale,
+        radius: POWER_UP_RADIUS * scale,
+    });
+    broadcast(game, message);
+}
+
+fn send_power_up_expired(game: &mut Game, power_up: &PowerUp) {
+    let message = ServerEvent::PowerUpExpired(PowerUpExpired { id: power_up.id });
+    broadcast(game, message);
+}
+
+fn send_power_up_collected(
+    game: &mut Game,
+    id: u32,
+    effect: &ActiveEffect,
+    player_id: &str,
+    power_ups: &PowerUps,
+) {
+    let message = ServerEvent::PowerUpCollected(PowerUpCollected {
+        id,
+        kind: effect.kind,
+        player_id: player_id.into(),
+        duration_ms: effect.kind.duration().map(|d| d.as_millis() as u64),
+        paddle_scale: power_ups.paddle_scale(effect.paddle),
+        shielded: power_ups.is_shielded(effect.side),
+    });
+    broadcast(game, message);
+}
+
+fn send_power_up_ended(
+    game: &mut Game,
+    effect: &ActiveEffect,
+    player_id: &str,
+    power_ups: &PowerUps,
+) {
+    let message = ServerEvent::PowerUpEnded(PowerUpEnded {
+        kind: effect.kind,
+        player_id: player_id.into(),
+        paddle_scale: power_ups.paddle_scale(effect.paddle),
+        shielded: power_ups.is_shielded(effect.side),
+    });
+    broadcast(game, message);
+}
+
+fn send_game_over(game: &mut Game) {
+    let message = ServerEvent::GameOver(GameOver {
+        winners: game.winners().into_iter().map(String::from).collect(),
+        p1_score: score(game, PLAYER_1),
+        p2_score: score(game, PLAYER_2),
+        clock: clock_ms(game),
+        stats: stats_summary(game),
+    });
+    broadcast(game, message);
+}
+
+/// The match's statistics with speeds and distances in pixels.
+fn stats_summary(game: &Game) -> StatsSummary {
+    let scale = game.settings.scaling_factor;
+    let stats = &game.stats;
+    StatsSummary {
+        longest_rally: stats.longest_rally,
+        average_rally: stats.average_rally(),
+        fastest_ball: stats.fastest_ball * scale,
+        hits: stats.hits.clone(),
+        points_on_serve: stats.points_on_serve.clone(),
+        time_in_play_ms: stats.time_in_play.as_millis() as u64,
+        paddle_distance: stats
+            .paddle_distance
+            .iter()
+            .map(|(id, distance)| (id.clone(), distance * scale))
+            .collect(),
+    }
+}
+
+fn send_rematch_requested(game: &mut Game, requester_id: &str) {
+    let message = ServerEvent::RematchRequested(RematchRequested {
+        player_id: requester_id.into(),
+        swap_sides: game.swap_sides_on_rematch,
+    });
+    let to = game
+        .players
+        .keys()
+        .filter(|id| id.as_str() != requester_id)
+        .cloned()
+        .collect();
+    game.outbox.push(Outgoing { to, message });
+}
+
+fn scaled_positions(game: &Game) -> HashMap<String, Position> {
+    let scale = game.settings.scaling_factor;
+    game.players
+        .iter()
+        .map(|(id, p)| {
+            let position = Position {
+                x: p.x * scale,
+                y: p.y * scale,
+            };
+            (id.clone(), position)
+        })
+        .collect()
+}
+
+/// Points for `player_id`, nothing if there is no such player in this mode.
+fn score(game: &Game, player_id: &str) -> u32 {
+    game.players.get(player_id).map_or(0, |p| p.score)
+}
+
+fn scores(game: &Game) -> Scores {
+    Scores {
+        p1_score: score(game, PLAYER_1),
+        p2_score: score(game, PLAYER_2),
+        clock: clock_ms(game),
+        lives: game
+            .players
+            .iter()
+            .map(|(id, p)| (id.clone(), p.lives))
+            .collect(),
+    }
+}
+
+/// Milliseconds left on the match clock, null if the match isn't timed.
+fn clock_ms(game: &Game) -> Option<u64> {
+    game.time_left.map(|t| t.as_millis() as u64)
+}
+
+fn send_rematch_started(game: &mut Game, swap_sides: bool) {
+    let message = ServerEvent::RematchStarted(RematchStarted {
+        swap_sides,
+        players: scaled_positions(game),
+    });
+    broadcast(game, message);
+}
+
+fn broadcast(game: &mut Game, message: ServerEvent) {
+    broadcast_to(game, |_| true, message);
+}
+
+fn broadcast_to(game: &mut Game, to: impl Fn(&Player) -> bool, message: ServerEvent) {
+    let to = game
+        .players
+        .iter()
+        .filter(|(_, p)| to(p))
+        .map(|(id, _)| id.clone())
+        .collect();
+    game.outbox.push(Outgoing { to, message });
+}
+
+fn send_action_late(game: &mut Game, player_id: &str, tick: u64) {
+    let message = ServerEvent::ActionLate(ActionLate {
+        tick,
+        latest_tick: game.tick,
+        late_actions: game.get_player(player_id).late_actions,
+    });
+    game.outbox.push(Outgoing {
+        to: vec![player_id.into()],
+        message,
+    });
+}
+
+fn send_update_screen(game: &mut Game, step_result: &StepResult) {
+    let scale = game.settings.scaling_factor;
+    let balls = step_result
+        .balls
+        .iter()
+        .map(|b| BallPosition {
+            id: b.id,
+            x: b.x * scale,
+            y: b.y * scale,
+        })
+        .collect();
+    let obstacles = step_result
+        .obstacles
+        .iter()
+        .map(|&(index, x, y)| ObstaclePosition {
+            index,
+            x: x * scale,
+            y: y * scale,
+        })
+        .collect();
+
+    let message = ServerEvent::UpdateScreen(UpdateScreen {
+        balls,
+        obstacles,
+        players: scaled_positions(game),
+        clock: clock_ms(game),
+    });
+    broadcast_to(game, |p| !p.bot, message);
+}
+
+/// Sent to bots every tick in place of `update_screen`, in arena units.
+fn send_observation(game: &mut Game, step_result: &StepResult) {
+    let balls = step_result
+        .balls
+        .iter()
+        .map(|b| ObservedBall {
+            id: b.id,
+            x: b.x,
+            y: b.y,
+            vx: b.vx,
+            vy: b.vy,
+        })
+        .collect();
+    let paddles = game
+        .players
+        .iter()
+        .map(|(id, p)| {
+            let paddle = ObservedPaddle {
+                x: p.x,
+                y: p.y,
+                length: p.paddle_length(&game.settings),
+                side: p.side,
+                score: p.score,
+                lives: p.lives,
+            };
+            (id.clone(), paddle)
+        })
+        .collect();
+
+    let message = ServerEvent::Observation(Observation {
+        tick: game.tick,
+        balls,
+        paddles,
+        clock: clock_ms(game),
+    });
+    broadcast_to(game, |p| p.bot, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player::Handicap, pong::Orientation, settings::BallSettings};
+
+    #[test]
+    fn both_players_ready_true_when_both_ready() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+        game.set_player_ready("p1");
+        game.set_player_ready("p2");
+
+        assert!(game.both_players_ready())
+    }
+
+    #[test]
+    fn players_can_only_be_removed_before_kick_off() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+
+        assert!(game.remove_player("p1"));
+        assert!(!game.remove_player("p1"));
+        assert!(game.is_slot_free("p1"));
+        assert_eq!(game.add_player(), "p1");
+
+        game.start();
+        assert!(!game.remove_player("p2"));
+        assert_eq!(game.players().len(), 2);
+        assert!(game.kick_player("p2"));
+        assert!(game.is_kicked("p2") && !game.is_kicked("p1"));
+    }
+
+    #[test]
+    fn running_out_of_time_is_not_a_point() {
+        let settings = MatchSettings {
+            time_limit: Some(Duration::from_millis(1)),
+            ..Default::default()
+        };
+        let mut game = Game::new("123".into(), GameMode::Singles, settings);
+        game.add_player();
+        game.add_player();
+        game.score_point(Side::Left);
+        game.start();
+        let mut engine = Engine::new(&game);
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(engine.tick(&mut game));
+        assert_eq!(game.winners(), vec![PLAYER_1]);
+        assert_eq!(game.stats.points, 0);
+        assert!(!game
+            .take_outbox()
+            .iter()
+            .any(|o| matches!(o.message, ServerEvent::EndPoint(_))));
+    }
+
+    #[test]
+    fn holding_serve_pauses_the_match_once_the_point_ends() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+        game.start();
+        let mut engine = Engine::new(&game);
+        engine.tick(&mut game);
+        game.hold_serve();
+
+        // a ball about to go in at the left wall, away from p1's paddle
+        engine.pong.restore_balls(&[BallSnapshot {
+            x: 2.0,
+            y: 2.0,
+            vx: -120.0,
+            vy: 0.0,
+            last_touched: None,
+            rally: 0,
+            curve: 0.0,
+        }]);
+        for _ in 0..10 {
+            assert!(!engine.tick(&mut game));
+        }
+
+        assert_eq!(game.stats.points, 1);
+        assert_eq!(game.get_player(PLAYER_2).score, 1);
+        assert_eq!(engine.pong.num_balls(), 0);
+        assert!(game.snapshot().balls.is_empty());
+
+        // picked up again, the next point is served just as it would have been
+        let serve = game.new_pong().ball_snapshots();
+        let mut restored = Game::restore(game.snapshot());
+        restored.start();
+        assert_eq!(Engine::new(&restored).pong.ball_snapshots(), serve);
+    }
+
+    #[test]
+    fn every_ball_scores_and_the_point_ends_with_the_last() {
+        let settings = MatchSettings {
+            balls: BallSettings {
+                count: 2,
+                spawn_every: None,
+            },
+            ..Default::default()
+        };
+        let mut game = Game::new("123".into(), GameMode::Singles, settings);
+        game.add_player();
+        game.add_player();
+        game.start();
+        let mut engine = Engine::new(&game);
+        // one ball about to go in at the left wall, one further out, both
+        // above p1's paddle
+        let ball = |x: f32| BallSnapshot {
+            x,
+            y: 2.0,
+            vx: -120.0,
+            vy: 0.0,
+            last_touched: None,
+            rally: 0,
+            curve: 0.0,
+        };
+        engine.pong.restore_balls(&[ball(2.0), ball(40.0)]);
+
+        let mut ids_on_screen = vec![];
+        let mut scored = vec![];
+        let mut end_points = 0;
+        for _ in 0..60 {
+            engine.tick(&mut game);
+            for outgoing in game.take_outbox() {
+                match outgoing.message {
+                    ServerEvent::UpdateScreen(update) => {
+                        ids_on_screen.push(update.balls.iter().map(|b| b.id).collect::<Vec<_>>())
+                    }
+                    ServerEvent::BallScored(ball_scored) => {
+                        assert_eq!(end_points, 0);
+                        scored.extend(ball_scored.ball_ids);
+                    }
+                    ServerEvent::EndPoint(_) => end_points += 1,
+                    _ => {}
+                }
+            }
+            if end_points > 0 {
+                break;
+            }
+        }
+
+        // the first ball went in on its own, the second ended the point
+        let (first, second) = (ids_on_screen[0][0], ids_on_screen[0][1]);
+        assert_eq!(scored, vec![first]);
+        assert_eq!(end_points, 1);
+        assert_eq!(game.get_player(PLAYER_2).score, 2);
+        assert_eq!(game.stats.points, 1);
+        // the second ball kept its id after the first had gone
+        assert!(ids_on_screen.contains(&vec![second]));
+        assert!(ids_on_screen
+            .iter()
+            .all(|ids| ids == &vec![first, second] || ids == &vec![second]));
+        // and the next point is served with both balls again
+        assert_eq!(engine.pong.num_balls(), 2);
+    }
+
+    #[test]
+    fn restored_games_carry_on_from_their_snapshot() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+        game.score_point(Side::Right);
+        game.start();
+        let mut engine = Engine::new(&game);
+        for _ in 0..30 {
+            engine.tick(&mut game);
+        }
+
+        let json = serde_json::to_string(&game.snapshot()).unwrap();
+        let mut restored = Game::restore(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.status, GameStatus::Waiting);
+        assert_eq!(restored.get_player("p2").score, 1);
+        assert!(!restored.both_players_ready());
+
+        let saved = game.balls[0];
+        restored.start();
+        Engine::new(&restored).tick(&mut restored);
+        let ball = restored.balls[0];
+        assert!((ball.x - saved.x).abs() < 1.0 && (ball.y - saved.y).abs() < 1.0);
+        assert!(restored.stats.time_in_play >= game.stats.time_in_play);
+    }
+
+    #[test]
+    fn accept_rematch_resets_scores_and_swaps_sides() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+        for _ in 0..POINTS_TO_WIN {
+            game.score_point(Side::Left);
+        }
+        game.status = GameStatus::Finished;
+
+        assert_eq!(game.winners(), vec![PLAYER_1]);
+        assert!(!game.start());
+        assert!(!game.accept_rematch(PLAYER_2));
+        assert!(game.request_rematch(PLAYER_1, true));
+        assert!(!game.accept_rematch(PLAYER_1));
+        assert!(game.accept_rematch(PLAYER_2));
+
+        assert_eq!(game.winning_side(), None);
+        assert!(game.start());
+        assert_eq!(game.get_player(PLAYER_1).side, Side::Right);
+        assert_eq!(game.get_player(PLAYER_2).side, Side::Left);
+        assert_eq!(
+            game.get_player(PLAYER_1).x,
+            Side::Right
+                .start(Lane::Full, game.settings.player_height, &game.settings)
+                .0
+        );
+    }
+
+    #[test]
+    fn rematches_only_start_once_every_player_wants_one() {
+        let mut game = Game::new("123".into(), GameMode::Doubles, MatchSettings::default());
+        for _ in 0..4 {
+            game.add_player();
+        }
+        game.status = GameStatus::Finished;
+
+        assert!(game.request_rematch(PLAYER_1, false));
+        assert!(!game.accept_rematch(PLAYER_2));
+        assert!(!game.accept_rematch(PLAYER_3));
+        assert_eq!(game.status, GameStatus::Finished);
+        assert!(game.accept_rematch(PLAYER_4));
+        assert_eq!(game.status, GameStatus::Waiting);
+    }
+
+    #[test]
+    fn handicaps_apply_to_their_player() {
+        let mut settings = MatchSettings::default();
+        settings.handicaps.insert(
+            String::from(PLAYER_2),
+            Handicap {
+                paddle_scale: 2.0,
+                head_start: 2,
+                serves: true,
+                ..Handicap::default()
+            },
+        );
+        let mut game = Game::new("123".into(), GameMode::Singles, settings);
+        game.add_player();
+        game.add_player();
+
+        assert_eq!(game.get_player(PLAYER_1).score, 0);
+        assert_eq!(game.get_player(PLAYER_2).score, 2);
+
+        game.update_player_pos(PLAYER_2, None, Some(1000.0));
+        assert_eq!(
+            game.get_player(PLAYER_2).y,
+            game.settings.height - 2.0 * game.settings.player_height
+        );
+
+        let mut pong = game.new_pong();
+        let ball = &pong.next(None)[0];
+        assert!(ball.x < game.settings.ball_start().0);
+    }
+
+    #[test]
+    fn bot_actions_only_count_for_the_latest_tick() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.set_player_bot(PLAYER_1);
+        game.tick = 2;
+        game.tick_sent = Some(Instant::now());
+
+        assert!(game.act(PLAYER_1, 2, 10.0));
+        assert_eq!(game.get_player(PLAYER_1).y, 10.0);
+
+        assert!(!game.act(PLAYER_1, 1, 20.0));
+        game.tick_sent = Some(Instant::now() - TICK_DEADLINE * 2);
+        assert!(!game.act(PLAYER_1, 2, 20.0));
+        assert_eq!(game.get_player(PLAYER_1).y, 10.0);
+        assert_eq!(game.get_player(PLAYER_1).late_actions, 2);
+    }
+
+    #[test]
+    fn bots_cannot_move_without_acting() {
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        game.add_player();
+        game.add_player();
+        game.set_player_bot(PLAYER_1);
+        let y = game.get_player(PLAYER_1).y;
+
+        game.update_player_pos(PLAYER_1, None, Some(y + 10.0));
+        assert_eq!(game.get_player(PLAYER_1).y, y);
+
+        game.update_player_pos(PLAYER_2, None, Some(y + 10.0));
+        assert_eq!(game.get_player(PLAYER_2).y, y + 10.0);
+    }
+
+    #[test]
+    fn doubles_fills_four_slots_and_scores_by_team() {
+        let mut game = Game::new("123".into(), GameMode::Doubles, MatchSettings::default());
+        for _ in 0..4 {
+            game.add_player();
+        }
+        assert!(game.is_full());
+        assert_eq!(game.get_player(PLAYER_3).side, Side::Left);
+        assert_eq!(game.get_player(PLAYER_3).lane, Lane::Bottom);
+
+        game.update_player_pos(PLAYER_1, None, Some(1000.0));
+        assert_eq!(
+            game.get_player(PLAYER_1).y,
+            Lane::Top
+                .bounds(
+                    Orientation::Vertical,
+                    game.settings.player_height,
+                    &game.settings
+                )
+                .1
+        );
+
+        for _ in 0..POINTS_TO_WIN {
+            game.score_point(Side::Right);
+        }
+        let mut winners = game.winners();
+        winners.sort();
+        assert_eq!(winners, vec![PLAYER_2, PLAYER_4]);
+    }
+
+    #[test]
+    fn timed_match_goes_to_sudden_death_when_tied() {
+        let settings = MatchSettings {
+            time_limit: Some(Duration::from_secs(60)),
+            ..MatchSettings::default()
+        };
+        let mut game = Game::new("123".into(), GameMode::Singles, settings);
+        game.add_player();
+        game.add_player();
+        for _ in 0..POINTS_TO_WIN {
+            game.score_point(Side::Left);
+            game.score_point(Side::Right);
+        }
+
+        assert!(!game.tick_clock(Duration::from_secs(30)));
+        assert_eq!(game.time_left, Some(Duration::from_secs(30)));
+        assert_eq!(game.winning_side(), None);
+
+        assert!(game.tick_clock(Duration::from_secs(61)));
+        assert!(!game.tick_clock(Duration::from_secs(62)));
+        assert_eq!(game.winning_side(), None);
+
+        assert!(game.concede(Side::Left));
+        assert_eq!(game.winners(), vec![PLAYER_2]);
+    }
+
+    #[test]
+    fn practice_starts_with_one_player_and_never_ends() {
+        let mut game = Game::new("123".into(), GameMode::Practice, MatchSettings::default());
+        game.add_player();
+        game.set_player_ready(PLAYER_1);
+        assert!(game.both_players_ready());
+
+        assert!(!game.concede(Side::Right));
+        assert!(game.concede(Side::Left));
+        assert_eq!(game.practice.misses, 1);
+        assert_eq!(game.winning_side(), None);
+        assert_eq!(game.new_pong().num_paddles(), 1);
+    }
+
+    #[test]
+    fn free_for_all_last_player_standing_wins() {
+        let mut game = Game::new("123".into(), GameMode::FreeForAll, MatchSettings::default());
+        for _ in 0..4 {
+            game.add_player();
+        }
+        assert_eq!(
+            game.get_player(PLAYER_3).orientation,
+            Orientation::Horizontal
+        );
+
+        for side in [Side::Left, Side::Right, Side::Top] {
+            for _ in 0..START_LIVES {
+                assert!(game.concede(side));
+            }
+            assert!(!game.concede(side));
+        }
+
+        assert!(game.get_player(PLAYER_1).is_eliminated());
+        assert_eq!(game.paddle_order(), vec![PLAYER_4]);
+        assert_eq!(game.winners(), vec![PLAYER_4]);
+    }
+}
@@ -0,0 +1,12 @@
+pub mod bot;
+pub mod client_event;
+pub mod environment;
+pub mod game;
+pub mod map;
+pub mod player;
+pub mod pong;
+pub mod power_up;
+pub mod practice;
+pub mod protocol;
+pub mod settings;
+pub mod stats;
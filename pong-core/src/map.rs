@@ -0,0 +1,244 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+pub const MAPS_DIR: &str = "maps";
+pub const DEFAULT_MAP: &str = "classic";
+
+lazy_static! {
+    pub static ref MAPS: HashMap<String, Map> = load_maps(MAPS_DIR);
+}
+
+/// An arena layout, read from a JSON file in `MAPS_DIR`. The four walls are
+/// always there, a map only adds obstacles between them. Coordinates are in
+/// arena units, the same as paddle and ball positions.
+///
+/// ```json
+/// {
+///   "name": "pillars",
+///   "obstacles": [
+///     { "shape": { "type": "cuboid", "half_width": 1.5, "half_height": 6 }, "x": 50, "y": 12 },
+///     { "shape": { "type": "ball", "radius": 3 }, "x": 50, "y": 55, "restitution": 1.1 },
+///     {
+///       "shape": { "type": "convex_polygon", "points": [[0, -4], [4, 0], [0, 4], [-4, 0]] },
+///       "x": 30, "y": 20,
+///       "path": { "points": [[30, 46]], "speed": 8 }
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Map {
+    pub name: String,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+}
+
+impl Map {
+    pub fn classic() -> Self {
+        Map {
+            name: String::from(DEFAULT_MAP),
+            obstacles: vec![],
+        }
+    }
+
+    /// Looks up a loaded map by name, falling back to the plain arena.
+    pub fn named(name: &str) -> Self {
+        MAPS.get(name).cloned().unwrap_or_else(Map::classic)
+    }
+
+    /// Maps are drawn up for the standard arena. This stretches every
+    /// position, size and speed by `factor` to fit a bigger or smaller one.
+    pub fn scaled(mut self, factor: f32) -> Self {
+        let scale = |(x, y): (f32, f32)| (x * factor, y * factor);
+
+        for obstacle in self.obstacles.iter_mut() {
+            (obstacle.x, obstacle.y) = scale((obstacle.x, obstacle.y));
+            obstacle.shape = match &obstacle.shape {
+                Shape::Cuboid {
+                    half_width,
+                    half_height,
+                } => Shape::Cuboid {
+                    half_width: half_width * factor,
+                    half_height: half_height * factor,
+                },
+                Shape::Ball { radius } => Shape::Ball {
+                    radius: radius * factor,
+                },
+                Shape::ConvexPolygon { points } => Shape::ConvexPolygon {
+                    points: points.iter().copied().map(scale).collect(),
+                },
+            };
+            if let Some(path) = obstacle.path.as_mut() {
+                path.points = path.points.iter().copied().map(scale).collect();
+                path.speed *= factor;
+            }
+        }
+        self
+    }
+
+    /// Checks that every moving obstacle has somewhere to go and a speed it
+    /// can get there at.
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            let Some(path) = &obstacle.path else {
+                continue;
+            };
+            if !path.speed.is_finite() || path.speed <= 0.0 {
+                return Err(format!(
+                    "obstacle {} moves at {}, it needs a positive speed",
+                    index, path.speed
+                ));
+            }
+            if path.points.is_empty() {
+                return Err(format!("obstacle {} has a path with no points", index));
+            }
+            if path
+                .points
+                .iter()
+                .any(|(x, y)| !x.is_finite() || !y.is_finite())
+            {
+                return Err(format!(
+                    "obstacle {} has a path point that isn't a number",
+                    index
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub shape: Shape,
+    /// Centre of the obstacle, or where it starts if it moves.
+    pub x: f32,
+    pub y: f32,
+    #[serde(default = "default_restitution")]
+    pub restitution: f32,
+    #[serde(default)]
+    pub path: Option<MotionPath>,
+}
+
+fn default_restitution() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    Cuboid {
+        half_width: f32,
+        half_height: f32,
+    },
+    Ball {
+        radius: f32,
+    },
+    /// Points are relative to the obstacle's centre.
+    ConvexPolygon {
+        points: Vec<(f32, f32)>,
+    },
+}
+
+/// Moving obstacles travel from their start through each of `points` in turn
+/// at `speed` units per second, then head back to the start and go round again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotionPath {
+    pub points: Vec<(f32, f32)>,
+    pub speed: f32,
+}
+
+/// Reads every `*.json` map in `dir`, keyed by map name. Files that fail to
+/// parse or validate are logged and skipped.
+pub fn load_maps(dir: impl AsRef<Path>) -> HashMap<String, Map> {
+    let mut maps = HashMap::from([(String::from(DEFAULT_MAP), Map::classic())]);
+
+    let Ok(entries) = fs::read_dir(dir.as_ref()) else {
+        log::error!("could not read maps from {:?}", dir.as_ref());
+        return maps;
+    };
+
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str::<Map>(&json).map_err(|e| e.to_string()))
+                .and_then(|map| map.validate().map(|()| map))
+            {
+                Ok(map) => {
+                    maps.insert(map.name.clone(), map);
+                }
+                Err(err) => log::error!("Failed to load map {:?}. Err: {}", path, err),
+            }
+        }
+    }
+
+    maps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_map() {
+        let json_data = r#"
+            {
+                "name": "test",
+                "obstacles": [
+                    { "shape": { "type": "ball", "radius": 3 }, "x": 50, "y": 33 },
+                    {
+                        "shape": { "type": "cuboid", "half_width": 1, "half_height": 5 },
+                        "x": 20,
+                        "y": 10,
+                        "restitution": 1.2,
+                        "path": { "points": [[20, 50]], "speed": 5 }
+                    }
+                ]
+            }
+        "#;
+
+        let map: Map = serde_json::from_str(json_data).unwrap();
+
+        assert_eq!(map.obstacles.len(), 2);
+        assert_eq!(map.obstacles[0].restitution, 1.0);
+        assert_eq!(map.obstacles[0].shape, Shape::Ball { radius: 3.0 });
+        assert_eq!(
+            map.obstacles[1].path.as_ref().unwrap().points,
+            vec![(20.0, 50.0)]
+        );
+    }
+
+    #[test]
+    fn paths_need_a_positive_speed_and_finite_points() {
+        let with_path = |points: Vec<(f32, f32)>, speed: f32| Map {
+            name: String::from("test"),
+            obstacles: vec![Obstacle {
+                shape: Shape::Ball { radius: 3.0 },
+                x: 20.0,
+                y: 10.0,
+                restitution: 1.0,
+                path: Some(MotionPath { points, speed }),
+            }],
+        };
+
+        assert!(with_path(vec![(20.0, 50.0)], 5.0).validate().is_ok());
+        for speed in [0.0, -5.0, f32::NAN, f32::INFINITY] {
+            assert!(with_path(vec![(20.0, 50.0)], speed).validate().is_err());
+        }
+        assert!(with_path(vec![], 5.0).validate().is_err());
+        assert!(with_path(vec![(20.0, f32::NAN)], 5.0).validate().is_err());
+        assert!(with_path(vec![(f32::INFINITY, 50.0)], 5.0)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn bundled_maps_load() {
+        let maps = load_maps(concat!(env!("CARGO_MANIFEST_DIR"), "/../maps"));
+
+        assert!(maps.contains_key(DEFAULT_MAP));
+        assert!(maps.len() > 1);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fmt, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pong::{Lane, Orientation, Side},
+    settings::MatchSettings,
+};
+
+/// Evens out a match between players of different skill. Picked by the host
+/// when creating the game.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Handicap {
+    /// Multiplies the length of the player's paddle.
+    pub paddle_scale: f32,
+    /// Fastest the paddle may move, in arena units per second.
+    pub max_speed: Option<f32>,
+    /// Points the player starts on, or extra lives in free-for-all.
+    pub head_start: u32,
+    /// Balls at the start of every point are served away from this player.
+    pub serves: bool,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            paddle_scale: 1.0,
+            max_speed: None,
+            head_start: 0,
+            serves: false,
+        }
+    }
+}
+
+impl fmt::Display for Handicap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if self.paddle_scale != 1.0 {
+            parts.push(format!("paddle x{}", self.paddle_scale));
+        }
+        if let Some(max_speed) = self.max_speed {
+            parts.push(format!("top speed {}", max_speed));
+        }
+        if self.head_start > 0 {
+            parts.push(format!("+{} head start", self.head_start));
+        }
+        if self.serves {
+            parts.push(String::from("serves"));
+        }
+        if parts.is_empty() {
+            parts.push(String::from("none"));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub x: f32,
+    pub y: f32,
+    pub side: Side,
+    pub lane: Lane,
+    pub orientation: Orientation,
+    pub score: u32,
+    /// Only used in free-for-all, where a player is out once this hits zero.
+    pub lives: Option<u32>,
+    pub handicap: Handicap,
+    pub is_ready: bool,
+    pub wants_rematch: bool,
+    /// Bots are sent observations instead of screen updates.
+    pub bot: bool,
+    /// Bot actions that missed their tick's deadline.
+    pub late_actions: u32,
+    /// Kicked out of a game in progress by an admin, and kept out.
+    pub kicked: bool,
+    #[serde(skip)]
+    last_moved: Option<Instant>,
+}
+
+impl Player {
+    pub fn new(
+        side: Side,
+        lane: Lane,
+        lives: Option<u32>,
+        handicap: Handicap,
+        settings: &MatchSettings,
+    ) -> Self {
+        let mut player = Player {
+            x: 0.0,
+            y: 0.0,
+            side,
+            lane,
+            orientation: side.orientation(),
+            score: 0,
+            lives: None,
+            handicap,
+            is_ready: false,
+            wants_rematch: false,
+            bot: false,
+            late_actions: 0,
+            kicked: false,
+            last_moved: None,
+        };
+        player.reset_score(lives);
+        player.reset_position(settings);
+        player
+    }
+
+    pub fn paddle_length(&self, settings: &MatchSettings) -> f32 {
+        settings.player_height * self.handicap.paddle_scale
+    }
+
+    /// Back to the start of a match with `lives` (if playing for lives) plus
+    /// any head start.
+    pub fn reset_score(&mut self, lives: Option<u32>) {
+        match lives {
+            Some(lives) => {
+                self.score = 0;
+                self.lives = Some(lives + self.handicap.head_start);
+            }
+            None => {
+                self.score = self.handicap.head_start;
+                self.lives = None;
+            }
+        }
+    }
+
+    pub fn reset_position(&mut self, settings: &MatchSettings) {
+        self.orientation = self.side.orientation();
+        (self.x, self.y) = self
+            .side
+            .start(self.lane, self.paddle_length(settings), settings);
+    }
+
+    /// Moves the paddle along its wall, ignoring the coordinate it can't move
+    /// in. A speed handicap limits how far it can get since the last move.
+    pub fn move_to(&mut self, x: Option<f32>, y: Option<f32>, settings: &MatchSettings) {
+        let length = self.paddle_length(settings);
+        let now = Instant::now();
+        let max_step = self
+            .handicap
+            .max_speed
+            .zip(self.last_moved)
+            .map(|(speed, last)| speed * (now - last).as_secs_f32());
+        let limit = |from: f32, to: f32| match max_step {
+            Some(step) => to.clamp(from - step, from + step),
+            None => to,
+        };
+
+        match self.orientation {
+            Orientation::Vertical => {
+                if let Some(y) = y {
+                    self.y = self
+                        .lane
+                        .clamp(self.orientation, length, limit(self.y, y), settings);
+                }
+            }
+            Orientation::Horizontal => {
+                if let Some(x) = x {
+                    self.x = self
+                        .lane
+                        .clamp(self.orientation, length, limit(self.x, x), settings);
+                }
+            }
+        }
+        self.last_moved = Some(now);
+    }
+
+    pub fn is_eliminated(&self) -> bool {
+        self.lives == Some(0)
+    }
+}
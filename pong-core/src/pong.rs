@@ -0,0 +1,834 @@
+use nalgebra::Vector2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rapier2d::prelude::*;
+use rapier2d::{
+    na::{vector, Rotation2},
+    pipeline::PhysicsPipeline,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    map::{self, Obstacle},
+    settings::MatchSettings,
+};
+
+pub const PONG_WIDTH: f32 = 100.0;
+pub const PONG_HEIGHT: f32 = PONG_WIDTH / 3.0 * 2.0;
+pub const BALL_RADIUS: f32 = 1.2;
+pub const PLAYER_WIDTH: f32 = 1.3;
+pub const PLAYER_HEIGHT: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        match self {
+            Side::Left | Side::Right => Orientation::Vertical,
+            Side::Top | Side::Bottom => Orientation::Horizontal,
+        }
+    }
+
+    /// Top left corner of a paddle `paddle_length` long guarding this side,
+    /// centred in `lane`.
+    pub fn start(&self, lane: Lane, paddle_length: f32, settings: &MatchSettings) -> (f32, f32) {
+        let along = lane.start(self.orientation(), paddle_length, settings);
+        match self {
+            Side::Left => (0.0, along),
+            Side::Right => (settings.width - settings.player_width, along),
+            Side::Top => (along, 0.0),
+            Side::Bottom => (along, settings.height - settings.player_width),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+impl Orientation {
+    /// Width and height of a paddle `paddle_length` long with this orientation.
+    pub fn size(&self, paddle_length: f32, settings: &MatchSettings) -> (f32, f32) {
+        match self {
+            Orientation::Vertical => (settings.player_width, paddle_length),
+            Orientation::Horizontal => (paddle_length, settings.player_width),
+        }
+    }
+}
+
+/// The part of its side a paddle is allowed to move in. Singles paddles use
+/// the whole wall, doubles teams split it into top and bottom halves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lane {
+    Full,
+    Top,
+    Bottom,
+}
+
+impl Lane {
+    /// Smallest and largest position along its wall the top left corner of a
+    /// paddle `paddle_length` long in this lane can have. For horizontal
+    /// paddles `Top` and `Bottom` are the left and right halves.
+    pub fn bounds(
+        &self,
+        orientation: Orientation,
+        paddle_length: f32,
+        settings: &MatchSettings,
+    ) -> (f32, f32) {
+        let length = match orientation {
+            Orientation::Vertical => settings.height,
+            Orientation::Horizontal => settings.width,
+        };
+        let paddle = paddle_length;
+        match self {
+            Lane::Full => (0.0, length - paddle),
+            Lane::Top => (0.0, length / 2.0 - paddle),
+            Lane::Bottom => (length / 2.0, length - paddle),
+        }
+    }
+
+    pub fn start(
+        &self,
+        orientation: Orientation,
+        paddle_length: f32,
+        settings: &MatchSettings,
+    ) -> f32 {
+        let (min, max) = self.bounds(orientation, paddle_length, settings);
+        (min + max) / 2.0
+    }
+
+    pub fn clamp(
+        &self,
+        orientation: Orientation,
+        paddle_length: f32,
+        pos: f32,
+        settings: &MatchSettings,
+    ) -> f32 {
+        let (min, max) = self.bounds(orientation, paddle_length, settings);
+        pos.clamp(min, max)
+    }
+}
+
+fn create_wall_collider(h_width: f32, h_height: f32, x: f32, y: f32) -> Collider {
+    ColliderBuilder::cuboid(h_width, h_height)
+        .friction(0.0)
+        .translation(Vector2::new(x, y))
+        .build()
+}
+
+fn create_player_collider(h_width: f32, h_height: f32) -> Collider {
+    ColliderBuilder::cuboid(h_width, h_height)
+        .friction(0.0)
+        .restitution(1.2) // increase by 20% per shot
+        .restitution_combine_rule(CoefficientCombineRule::Max)
+        .build()
+}
+
+fn create_obstacle_collider(obstacle: &Obstacle) -> Option<Collider> {
+    let builder = match &obstacle.shape {
+        map::Shape::Cuboid {
+            half_width,
+            half_height,
+        } => ColliderBuilder::cuboid(*half_width, *half_height),
+        map::Shape::Ball { radius } => ColliderBuilder::ball(*radius),
+        map::Shape::ConvexPolygon { points } => {
+            let points: Vec<Point<f32>> = points.iter().map(|(x, y)| point![*x, *y]).collect();
+            ColliderBuilder::convex_hull(&points)?
+        }
+    };
+
+    let builder = if obstacle.path.is_some() {
+        builder
+    } else {
+        builder.translation(vector![obstacle.x, obstacle.y])
+    };
+
+    Some(
+        builder
+            .friction(0.0)
+            .restitution(obstacle.restitution)
+            .build(),
+    )
+}
+
+/// A serve's velocity, heading for the wall on `towards` or in a random
+/// direction.
+fn serve_velocity(rng: &mut impl Rng, towards: Option<Side>, speed_multiplier: f32) -> (f32, f32) {
+    let along = rng.gen_range(7.0..10.0) * speed_multiplier;
+    let across = rng.gen_range(2.5..4.0) * (if rng.gen() { 1.0 } else { -1.0 }) * speed_multiplier;
+    match towards {
+        Some(Side::Left) => (-along, across),
+        Some(Side::Right) => (along, across),
+        Some(Side::Top) => (across, -along),
+        Some(Side::Bottom) => (across, along),
+        None => (along * (if rng.gen() { 1.0 } else { -1.0 }), across),
+    }
+}
+
+struct PongPhysicsHooks;
+
+impl PhysicsHooks for PongPhysicsHooks {
+    fn modify_solver_contacts(&self, context: &mut ContactModificationContext) {
+        for _solver_contact in &mut *context.solver_contacts {}
+    }
+}
+
+/// Where a ball is after a step, which wall it is touching and which sensor it
+/// is passing through, if any. `last_touched` is the index of the paddle that
+/// last hit it, and `rally` how many times paddles have hit it since it was
+/// served.
+#[derive(Debug, Clone, Copy)]
+pub struct BallState {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    /// Arena units per second.
+    pub vx: f32,
+    pub vy: f32,
+    pub wall_contact: Option<Side>,
+    pub sensor_contact: Option<u32>,
+    pub last_touched: Option<usize>,
+    /// Paddle the ball hit this step, only set on the step contact starts.
+    pub paddle_hit: Option<usize>,
+    /// Where along `paddle_hit` it hit, from -1 at the top (or left) end to 1
+    /// at the other.
+    pub hit_offset: Option<f32>,
+    /// Wall the ball hit this step, only set on the step contact starts.
+    pub wall_hit: Option<Side>,
+    pub rally: u32,
+}
+
+/// A ball in play as saved by `Pong::ball_snapshots`, for carrying on with
+/// it in another `Pong`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BallSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub last_touched: Option<usize>,
+    pub rally: u32,
+    pub curve: f32,
+}
+
+struct Ball {
+    id: u32,
+    body_handle: RigidBodyHandle,
+    collider_handle: ColliderHandle,
+    last_touched: Option<usize>,
+    /// Paddle the ball is in contact with, and the one it hit this step.
+    touching: Option<usize>,
+    paddle_hit: Option<usize>,
+    hit_offset: Option<f32>,
+    /// Same again for walls.
+    touching_wall: Option<Side>,
+    wall_hit: Option<Side>,
+    rally: u32,
+    /// Radians the ball's velocity is turned by every step.
+    curve: f32,
+}
+
+struct Paddle {
+    body_handle: RigidBodyHandle,
+    collider_handle: ColliderHandle,
+    h_width: f32,
+    h_height: f32,
+}
+
+struct MovingObstacle {
+    /// Position of the obstacle in the map it came from.
+    index: usize,
+    body_handle: RigidBodyHandle,
+    route: Vec<Vector2<f32>>,
+    target: usize,
+    speed: f32,
+}
+
+struct Sensor {
+    id: u32,
+    collider_handle: ColliderHandle,
+}
+
+pub struct Pong {
+    rigid_body_set: RigidBodySet,
+    collider_set: ColliderSet,
+    integration_parameters: IntegrationParameters,
+    physics_pipeline: PhysicsPipeline,
+    island_manager: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    impulse_joint_set: ImpulseJointSet,
+    multibody_joint_set: MultibodyJointSet,
+    ccd_solver: CCDSolver,
+    physics_hooks: PongPhysicsHooks,
+    paddles: Vec<Paddle>,
+    balls: Vec<Ball>,
+    next_ball_id: u32,
+    sensors: Vec<Sensor>,
+    next_sensor_id: u32,
+    moving_obstacles: Vec<MovingObstacle>,
+    speed_multiplier: f32,
+    width: f32,
+    height: f32,
+    ball_radius: f32,
+    wall_collider_handles: Vec<(Side, ColliderHandle)>,
+    /// Decides everything left to chance in the arena, so the same seed
+    /// serves the same balls.
+    rng: StdRng,
+}
+
+impl Pong {
+    /// Builds the arena described by `settings` with one paddle per entry in
+    /// `paddles`, each given as the top left corner of the paddle, which way it
+    /// is facing and how long it is, adds the map's obstacles and serves the
+    /// starting balls from the centre, towards `serve_to` if given. Chance is
+    /// decided by `seed`.
+    pub fn new(
+        speed_multiplier: f32,
+        paddles: &[(f32, f32, Orientation, f32)],
+        serve_to: Option<Side>,
+        settings: &MatchSettings,
+        seed: u64,
+    ) -> Self {
+        let h_width = settings.width / 2.0;
+        let h_height = settings.height / 2.0;
+        let h_wall_thickness = 25.0;
+
+        let mut rigid_body_set = RigidBodySet::new();
+        let mut collider_set = ColliderSet::new();
+
+        // left wall
+        let left_wall_collider_handle = collider_set.insert(create_wall_collider(
+            h_wall_thickness,
+            h_height,
+            0.0 - h_wall_thickness,
+            h_height,
+        ));
+
+        // right wall
+        let right_wall_collider_handle = collider_set.insert(create_wall_collider(
+            h_wall_thickness,
+            h_height,
+            settings.width + h_wall_thickness,
+            h_height,
+        ));
+
+        // top wall
+        let top_wall_collider_handle = collider_set.insert(create_wall_collider(
+            h_width,
+            h_wall_thickness,
+            h_width,
+            0.0 - h_wall_thickness,
+        ));
+
+        // bottom wall
+        let bottom_wall_collider_handle = collider_set.insert(create_wall_collider(
+            h_width,
+            h_wall_thickness,
+            h_width,
+            settings.height + h_wall_thickness,
+        ));
+
+        let paddles = paddles
+            .iter()
+            .map(|(x, y, orientation, length)| {
+                let (player_width, player_height) = orientation.size(*length, settings);
+                let h_player_width = player_width / 2.0;
+                let h_player_height = player_height / 2.0;
+
+                let body_handle = rigid_body_set.insert(
+                    RigidBodyBuilder::kinematic_position_based()
+                        .translation(vector![x + h_player_width, y + h_player_height])
+                        .build(),
+                );
+
+                let collider_handle = collider_set.insert_with_parent(
+                    create_player_collider(h_player_width, h_player_height),
+                    body_handle,
+                    &mut rigid_body_set,
+                );
+
+                Paddle {
+                    body_handle,
+                    collider_handle,
+                    h_width: h_player_width,
+                    h_height: h_player_height,
+                }
+            })
+            .collect();
+
+        let mut moving_obstacles = vec![];
+        for (index, obstacle) in settings.map.obstacles.iter().enumerate() {
+            let Some(collider) = create_obstacle_collider(obstacle) else {
+                log::error!("skipping obstacle {} with a degenerate shape", index);
+                continue;
+            };
+
+            match &obstacle.path {
+                Some(path) => {
+                    let body_handle = rigid_body_set.insert(
+                        RigidBodyBuilder::kinematic_position_based()
+                            .translation(vector![obstacle.x, obstacle.y])
+                            .build(),
+                    );
+                    collider_set.insert_with_parent(collider, body_handle, &mut rigid_body_set);
+
+                    let route = std::iter::once((obstacle.x, obstacle.y))
+                        .chain(path.points.iter().copied())
+                        .map(|(x, y)| vector![x, y])
+                        .collect();
+                    moving_obstacles.push(MovingObstacle {
+                        index,
+                        body_handle,
+                        route,
+                        target: 1,
+                        speed: path.speed,
+                    });
+                }
+                None => {
+                    collider_set.insert(collider);
+                }
+            }
+        }
+
+        let mut pong = Self {
+            rigid_body_set,
+            collider_set,
+            integration_parameters: IntegrationParameters::default(),
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            physics_hooks: PongPhysicsHooks {},
+            paddles,
+            balls: vec![],
+            next_ball_id: 0,
+            sensors: vec![],
+            next_sensor_id: 0,
+            moving_obstacles,
+            speed_multiplier,
+            width: settings.width,
+            height: settings.height,
+            ball_radius: settings.ball_radius,
+            wall_collider_handles: vec![
+                (Side::Left, left_wall_collider_handle),
+                (Side::Right, right_wall_collider_handle),
+                (Side::Top, top_wall_collider_handle),
+                (Side::Bottom, bottom_wall_collider_handle),
+            ],
+            rng: StdRng::seed_from_u64(seed),
+        };
+
+        for _ in 0..settings.balls.count {
+            pong.serve_ball(serve_to);
+        }
+
+        pong
+    }
+
+    /// Serves a new ball from the centre in a random direction and returns its id.
+    pub fn spawn_ball(&mut self) -> u32 {
+        self.serve_ball(None)
+    }
+
+    /// Serves a new ball from the centre, heading for the wall on `towards`
+    /// or in a random direction, and returns its id.
+    fn serve_ball(&mut self, towards: Option<Side>) -> u32 {
+        let (vx, vy) = serve_velocity(&mut self.rng, towards, self.speed_multiplier);
+        self.launch_ball(self.width / 2.0, self.height / 2.0, vx, vy)
+    }
+
+    /// Like `serve_ball`, picking the direction with `rng` instead of the
+    /// arena's own.
+    pub fn serve_ball_with(&mut self, rng: &mut impl Rng, towards: Option<Side>) -> u32 {
+        let (vx, vy) = serve_velocity(rng, towards, self.speed_multiplier);
+        self.launch_ball(self.width / 2.0, self.height / 2.0, vx, vy)
+    }
+
+    /// For anything else in the arena left to chance, like where power-ups
+    /// land.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Puts a new ball at `x`, `y` moving at `vx`, `vy` units per second and
+    /// returns its id.
+    pub fn launch_ball(&mut self, x: f32, y: f32, vx: f32, vy: f32) -> u32 {
+        let ball_body_handle = self.rigid_body_set.insert(
+            RigidBodyBuilder::dynamic()
+                .ccd_enabled(true)
+                .translation(vector![x, y])
+                .linvel(vector![vx, vy])
+                .build(),
+        );
+
+        let ball_collider_handle = self.collider_set.insert_with_parent(
+            ColliderBuilder::ball(self.ball_radius)
+                .restitution(1.0)
+                .restitution_combine_rule(CoefficientCombineRule::Max)
+                .friction(0.0)
+                .active_hooks(ActiveHooks::MODIFY_SOLVER_CONTACTS)
+                .build(),
+            ball_body_handle,
+            &mut self.rigid_body_set,
+        );
+
+        let id = self.next_ball_id;
+        self.next_ball_id += 1;
+        self.balls.push(Ball {
+            id,
+            body_handle: ball_body_handle,
+            collider_handle: ball_collider_handle,
+            last_touched: None,
+            touching: None,
+            paddle_hit: None,
+            hit_offset: None,
+            touching_wall: None,
+            wall_hit: None,
+            rally: 0,
+            curve: 0.0,
+        });
+        id
+    }
+
+    pub fn remove_ball(&mut self, id: u32) {
+        if let Some(i) = self.balls.iter().position(|b| b.id == id) {
+            let ball = self.balls.remove(i);
+            self.rigid_body_set.remove(
+                ball.body_handle,
+                &mut self.island_manager,
+                &mut self.collider_set,
+                &mut self.impulse_joint_set,
+                &mut self.multibody_joint_set,
+                true,
+            );
+        }
+    }
+
+    pub fn ball_snapshots(&self) -> Vec<BallSnapshot> {
+        self.balls
+            .iter()
+            .map(|ball| {
+                let body = &self.rigid_body_set[ball.body_handle];
+                BallSnapshot {
+                    x: body.translation().x,
+                    y: body.translation().y,
+                    vx: body.linvel().x,
+                    vy: body.linvel().y,
+                    last_touched: ball.last_touched,
+                    rally: ball.rally,
+                    curve: ball.curve,
+                }
+            })
+            .collect()
+    }
+
+    pub fn remove_balls(&mut self) {
+        let ids: Vec<u32> = self.balls.iter().map(|b| b.id).collect();
+        for id in ids {
+            self.remove_ball(id);
+        }
+    }
+
+    /// Swaps the balls in play for the ones in `balls`, which get new ids.
+    pub fn restore_balls(&mut self, balls: &[BallSnapshot]) {
+        self.remove_balls();
+        for snapshot in balls {
+            let id = self.launch_ball(snapshot.x, snapshot.y, snapshot.vx, snapshot.vy);
+            if let Some(ball) = self.balls.iter_mut().find(|b| b.id == id) {
+                ball.last_touched = snapshot.last_touched;
+                ball.rally = snapshot.rally;
+                ball.curve = snapshot.curve;
+            }
+        }
+    }
+
+    pub fn num_balls(&self) -> usize {
+        self.balls.len()
+    }
+
+    pub fn num_paddles(&self) -> usize {
+        self.paddles.len()
+    }
+
+    pub fn arena_size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// Current centres of the obstacles that move, with their index in the map.
+    pub fn moving_obstacle_positions(&self) -> Vec<(usize, f32, f32)> {
+        self.moving_obstacles
+            .iter()
+            .map(|o| {
+                let translation = self.rigid_body_set[o.body_handle].translation();
+                (o.index, translation.x, translation.y)
+            })
+            .collect()
+    }
+
+    /// Adds a round sensor balls pass straight through, reported in
+    /// `BallState::sensor_contact` while they overlap it. Returns its id.
+    pub fn spawn_sensor(&mut self, x: f32, y: f32, radius: f32) -> u32 {
+        let collider_handle = self.collider_set.insert(
+            ColliderBuilder::ball(radius)
+                .sensor(true)
+                .translation(vector![x, y])
+                .build(),
+        );
+
+        let id = self.next_sensor_id;
+        self.next_sensor_id += 1;
+        self.sensors.push(Sensor {
+            id,
+            collider_handle,
+        });
+        id
+    }
+
+    pub fn remove_sensor(&mut self, id: u32) {
+        if let Some(i) = self.sensors.iter().position(|s| s.id == id) {
+            let sensor = self.sensors.remove(i);
+            self.collider_set.remove(
+                sensor.collider_handle,
+                &mut self.island_manager,
+                &mut self.rigid_body_set,
+                true,
+            );
+        }
+    }
+
+    /// Stretches the long side of a paddle's collider by `scale`, keeping it
+    /// centred on the same spot.
+    pub fn set_paddle_scale(&mut self, paddle: usize, scale: f32) {
+        if let Some(paddle) = self.paddles.get(paddle) {
+            let shape = if paddle.h_height > paddle.h_width {
+                SharedShape::cuboid(paddle.h_width, paddle.h_height * scale)
+            } else {
+                SharedShape::cuboid(paddle.h_width * scale, paddle.h_height)
+            };
+            self.collider_set[paddle.collider_handle].set_shape(shape);
+        }
+    }
+
+    pub fn scale_ball_speed(&mut self, ball_id: u32, factor: f32) {
+        if let Some(ball) = self.balls.iter().find(|b| b.id == ball_id) {
+            let body = &mut self.rigid_body_set[ball.body_handle];
+            let linvel = *body.linvel() * factor;
+            body.set_linvel(linvel, true);
+        }
+    }
+
+    /// Makes a ball swing round by `curve` radians every step. Zero flies straight.
+    pub fn set_ball_curve(&mut self, ball_id: u32, curve: f32) {
+        if let Some(ball) = self.balls.iter_mut().find(|b| b.id == ball_id) {
+            ball.curve = curve;
+        }
+    }
+
+    /// Advances the world one tick, moving the paddles to `updated_positions`
+    /// (in the order they were given to `new`) first.
+    pub fn next(&mut self, updated_positions: Option<&[(f32, f32)]>) -> Vec<BallState> {
+        if let Some(positions) = updated_positions {
+            for (paddle, (x, y)) in self.paddles.iter().zip(positions) {
+                self.rigid_body_set[paddle.body_handle].set_next_kinematic_translation(vector![
+                    x + paddle.h_width,
+                    y + paddle.h_height
+                ]);
+            }
+        }
+
+        let dt = self.integration_parameters.dt;
+        for obstacle in self.moving_obstacles.iter_mut() {
+            let body = &mut self.rigid_body_set[obstacle.body_handle];
+            let target = obstacle.route[obstacle.target % obstacle.route.len()];
+            let to_target = target - body.translation();
+            let step = obstacle.speed * dt;
+
+            if to_target.norm() <= step {
+                body.set_next_kinematic_translation(target);
+                obstacle.target = (obstacle.target + 1) % obstacle.route.len();
+            } else {
+                body.set_next_kinematic_translation(
+                    body.translation() + to_target.normalize() * step,
+                );
+            }
+        }
+
+        for ball in self.balls.iter().filter(|b| b.curve != 0.0) {
+            let body = &mut self.rigid_body_set[ball.body_handle];
+            let linvel = Rotation2::new(ball.curve) * body.linvel();
+            body.set_linvel(linvel, true);
+        }
+
+        self.physics_pipeline.step(
+            &Vector::zeros(),
+            &self.integration_parameters,
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.rigid_body_set,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            &mut self.ccd_solver,
+            None,
+            &self.physics_hooks,
+            &(),
+        );
+
+        for i in 0..self.balls.len() {
+            let touching = self
+                .paddles
+                .iter()
+                .position(|p| self.ball_contact(&self.balls[i], p.collider_handle));
+            let touching_wall = self
+                .wall_collider_handles
+                .iter()
+                .find(|(_, handle)| self.ball_contact(&self.balls[i], *handle))
+                .map(|(side, _)| *side);
+            let offset = touching.map(|paddle| self.hit_offset(&self.balls[i], paddle));
+
+            let ball = &mut self.balls[i];
+            if touching.is_some() {
+                ball.last_touched = touching;
+            }
+            ball.paddle_hit = touching.filter(|paddle| ball.touching != Some(*paddle));
+            ball.touching = touching;
+            ball.hit_offset = offset.filter(|_| ball.paddle_hit.is_some());
+            if ball.paddle_hit.is_some() {
+                ball.rally += 1;
+            }
+            ball.wall_hit = touching_wall.filter(|side| ball.touching_wall != Some(*side));
+            ball.touching_wall = touching_wall;
+        }
+
+        self.balls
+            .iter()
+            .map(|ball| {
+                let body = &self.rigid_body_set[ball.body_handle];
+                let (translation, linvel) = (body.translation(), body.linvel());
+                BallState {
+                    id: ball.id,
+                    x: translation.x,
+                    y: translation.y,
+                    vx: linvel.x,
+                    vy: linvel.y,
+                    wall_contact: ball.touching_wall,
+                    sensor_contact: self
+                        .sensors
+                        .iter()
+                        .find(|sensor| {
+                            self.narrow_phase
+                                .intersection_pair(ball.collider_handle, sensor.collider_handle)
+                                == Some(true)
+                        })
+                        .map(|sensor| sensor.id),
+                    last_touched: ball.last_touched,
+                    paddle_hit: ball.paddle_hit,
+                    hit_offset: ball.hit_offset,
+                    wall_hit: ball.wall_hit,
+                    rally: ball.rally,
+                }
+            })
+            .collect()
+    }
+
+    /// Where `ball` is along `paddle`, from -1 at its top (or left) end to 1
+    /// at the other. Power-ups can have stretched the paddle's collider.
+    fn hit_offset(&self, ball: &Ball, paddle: usize) -> f32 {
+        let paddle = &self.paddles[paddle];
+        let ball_at = self.rigid_body_set[ball.body_handle].translation();
+        let paddle_at = self.rigid_body_set[paddle.body_handle].translation();
+        let half = self.collider_set[paddle.collider_handle]
+            .shape()
+            .as_cuboid()
+            .map_or(vector![paddle.h_width, paddle.h_height], |c| c.half_extents);
+        let offset = if paddle.h_height > paddle.h_width {
+            (ball_at.y - paddle_at.y) / half.y
+        } else {
+            (ball_at.x - paddle_at.x) / half.x
+        };
+        offset.clamp(-1.0, 1.0)
+    }
+
+    fn ball_contact(&self, ball: &Ball, collider_handle: ColliderHandle) -> bool {
+        self.narrow_phase
+            .contact_pair(ball.collider_handle, collider_handle)
+            .is_some_and(|cp| cp.has_any_active_contact)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::BallSettings;
+
+    #[test]
+    fn paddle_hits_report_where_they_landed_and_count_towards_the_rally() {
+        let mut pong = Pong::new(
+            6.0,
+            &[(2.0, 20.0, Orientation::Vertical, PLAYER_HEIGHT)],
+            None,
+            &MatchSettings::default(),
+            0,
+        );
+        for ball in pong.next(None) {
+            pong.remove_ball(ball.id);
+        }
+        // below the middle of the paddle, heading straight for it
+        let middle = 20.0 + PLAYER_HEIGHT / 2.0;
+        pong.launch_ball(20.0, middle + PLAYER_HEIGHT / 4.0, -40.0, 0.0);
+
+        let steps: Vec<BallState> = (0..240).map(|_| pong.next(None)[0]).collect();
+        let hits: Vec<&BallState> = steps.iter().filter(|b| b.paddle_hit.is_some()).collect();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].rally, 1);
+        let offset = hits[0].hit_offset.unwrap();
+        assert!((offset - 0.5).abs() < 0.1, "hit at {}", offset);
+
+        let walls: Vec<Side> = steps.iter().filter_map(|b| b.wall_hit).collect();
+        assert_eq!(walls, vec![Side::Right]);
+        assert_eq!(steps.last().unwrap().rally, 1);
+    }
+
+    #[test]
+    fn balls_keep_their_ids_as_others_come_and_go() {
+        let settings = MatchSettings {
+            balls: BallSettings {
+                count: 3,
+                spawn_every: None,
+            },
+            ..Default::default()
+        };
+        let mut pong = Pong::new(6.0, &[], None, &settings, 0);
+        let ids = |pong: &mut Pong| pong.next(None).iter().map(|b| b.id).collect::<Vec<_>>();
+        assert_eq!(ids(&mut pong), vec![0, 1, 2]);
+
+        pong.remove_ball(1);
+        assert_eq!(ids(&mut pong), vec![0, 2]);
+        // ids aren't handed out again within a point
+        assert_eq!(pong.spawn_ball(), 3);
+        assert_eq!(ids(&mut pong), vec![0, 2, 3]);
+        assert_eq!(pong.num_balls(), 3);
+    }
+}
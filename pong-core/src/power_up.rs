@@ -0,0 +1,256 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::{Pong, Side};
+
+pub const POWER_UP_RADIUS: f32 = 2.5;
+pub const POWER_UP_SPAWN_INTERVAL: Duration = Duration::from_secs(6);
+pub const POWER_UP_LIFETIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUpKind {
+    PaddleGrow,
+    PaddleShrink,
+    BallSpeedUp,
+    BallSlowDown,
+    BallCurve,
+    Shield,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::PaddleGrow,
+        PowerUpKind::PaddleShrink,
+        PowerUpKind::BallSpeedUp,
+        PowerUpKind::BallSlowDown,
+        PowerUpKind::BallCurve,
+        PowerUpKind::Shield,
+    ];
+
+    /// How long the effect lasts once collected. Speed changes are applied to
+    /// the ball once and don't wear off.
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            PowerUpKind::PaddleGrow | PowerUpKind::PaddleShrink => Some(Duration::from_secs(10)),
+            PowerUpKind::BallCurve => Some(Duration::from_secs(4)),
+            PowerUpKind::Shield => Some(Duration::from_secs(8)),
+            PowerUpKind::BallSpeedUp | PowerUpKind::BallSlowDown => None,
+        }
+    }
+
+    fn paddle_scale(&self) -> f32 {
+        match self {
+            PowerUpKind::PaddleGrow => 1.5,
+            PowerUpKind::PaddleShrink => 0.6,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A power-up sitting in the arena waiting to be collected.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerUp {
+    pub id: u32,
+    pub kind: PowerUpKind,
+    pub x: f32,
+    pub y: f32,
+    spawned_at: Instant,
+}
+
+/// A collected power-up that is still having an effect.
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub paddle: usize,
+    pub ball: u32,
+    pub side: Side,
+    expires_at: Instant,
+}
+
+/// Power-ups for a single point. Everything here refers to paddles and balls of
+/// one `Pong`, so it is thrown away along with it when the point ends.
+pub struct PowerUps {
+    on_field: Vec<PowerUp>,
+    active: Vec<ActiveEffect>,
+    last_spawn: Instant,
+}
+
+impl Default for PowerUps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PowerUps {
+    pub fn new() -> Self {
+        PowerUps {
+            on_field: vec![],
+            active: vec![],
+            last_spawn: Instant::now(),
+        }
+    }
+
+    /// Drops a random power-up somewhere in the middle of the arena if it has
+    /// been long enough since the last one.
+    pub fn spawn_due(&mut self, pong: &mut Pong) -> Option<PowerUp> {
+        if self.last_spawn.elapsed() < POWER_UP_SPAWN_INTERVAL {
+            return None;
+        }
+        self.last_spawn = Instant::now();
+
+        let (width, height) = pong.arena_size();
+        let rng = pong.rng();
+        let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+        let x = rng.gen_range(width * 0.25..width * 0.75);
+        let y = rng.gen_range(height * 0.1..height * 0.9);
+        let id = pong.spawn_sensor(x, y, POWER_UP_RADIUS);
+
+        let power_up = PowerUp {
+            id,
+            kind,
+            x,
+            y,
+            spawned_at: Instant::now(),
+        };
+        self.on_field.push(power_up);
+        Some(power_up)
+    }
+
+    /// Removes power-ups nobody collected in time and returns them.
+    pub fn expire_uncollected(&mut self, pong: &mut Pong) -> Vec<PowerUp> {
+        let (expired, remaining) = self
+            .on_field
+            .iter()
+            .partition(|p| p.spawned_at.elapsed() >= POWER_UP_LIFETIME);
+        self.on_field = remaining;
+
+        for power_up in &expired {
+            pong.remove_sensor(power_up.id);
+        }
+        expired
+    }
+
+    /// Gives the power-up with sensor `id` to whoever owns `paddle` and applies
+    /// it. `ball` is the ball that went through it and `side` the wall the
+    /// collecting player guards.
+    pub fn collect(
+        &mut self,
+        pong: &mut Pong,
+        id: u32,
+        paddle: usize,
+        ball: u32,
+        side: Side,
+    ) -> Option<ActiveEffect> {
+        let i = self.on_field.iter().position(|p| p.id == id)?;
+        let power_up = self.on_field.remove(i);
+        pong.remove_sensor(id);
+
+        let effect = ActiveEffect {
+            kind: power_up.kind,
+            paddle,
+            ball,
+            side,
+            expires_at: Instant::now() + power_up.kind.duration().unwrap_or_default(),
+        };
+
+        match effect.kind {
+            PowerUpKind::PaddleGrow | PowerUpKind::PaddleShrink => {
+                self.active.push(effect);
+                pong.set_paddle_scale(paddle, self.paddle_scale(paddle));
+            }
+            PowerUpKind::BallSpeedUp => pong.scale_ball_speed(ball, 1.4),
+            PowerUpKind::BallSlowDown => pong.scale_ball_speed(ball, 0.7),
+            PowerUpKind::BallCurve => {
+                self.active.push(effect);
+                let direction = if pong.rng().gen() { 1.0 } else { -1.0 };
+                pong.set_ball_curve(ball, 0.02 * direction);
+            }
+            PowerUpKind::Shield => self.active.push(effect),
+        }
+
+        Some(effect)
+    }
+
+    /// Undoes effects that have run out and returns them.
+    pub fn expire_effects(&mut self, pong: &mut Pong) -> Vec<ActiveEffect> {
+        let now = Instant::now();
+        let (ended, active): (Vec<ActiveEffect>, Vec<ActiveEffect>) =
+            self.active.iter().partition(|e| e.expires_at <= now);
+        self.active = active;
+
+        for effect in &ended {
+            match effect.kind {
+                PowerUpKind::PaddleGrow | PowerUpKind::PaddleShrink => {
+                    pong.set_paddle_scale(effect.paddle, self.paddle_scale(effect.paddle));
+                }
+                PowerUpKind::BallCurve if !self.is_curving(effect.ball) => {
+                    pong.set_ball_curve(effect.ball, 0.0);
+                }
+                _ => {}
+            }
+        }
+        ended
+    }
+
+    /// Combined size change from every grow and shrink active on `paddle`.
+    pub fn paddle_scale(&self, paddle: usize) -> f32 {
+        self.active
+            .iter()
+            .filter(|e| e.paddle == paddle)
+            .map(|e| e.kind.paddle_scale())
+            .product()
+    }
+
+    pub fn is_shielded(&self, side: Side) -> bool {
+        self.active
+            .iter()
+            .any(|e| e.kind == PowerUpKind::Shield && e.side == side)
+    }
+
+    fn is_curving(&self, ball: u32) -> bool {
+        self.active
+            .iter()
+            .any(|e| e.kind == PowerUpKind::BallCurve && e.ball == ball)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pong::{Orientation, PLAYER_HEIGHT},
+        settings::MatchSettings,
+    };
+
+    #[test]
+    fn collecting_grow_scales_paddle_until_it_wears_off() {
+        let mut pong = Pong::new(
+            6.0,
+            &[(0.0, 0.0, Orientation::Vertical, PLAYER_HEIGHT)],
+            None,
+            &MatchSettings::default(),
+            0,
+        );
+        let mut power_ups = PowerUps::new();
+        let id = pong.spawn_sensor(50.0, 30.0, POWER_UP_RADIUS);
+        power_ups.on_field.push(PowerUp {
+            id,
+            kind: PowerUpKind::PaddleGrow,
+            x: 50.0,
+            y: 30.0,
+            spawned_at: Instant::now(),
+        });
+
+        let effect = power_ups.collect(&mut pong, id, 0, 0, Side::Left);
+        assert_eq!(effect.map(|e| e.kind), Some(PowerUpKind::PaddleGrow));
+        assert_eq!(power_ups.paddle_scale(0), 1.5);
+        assert!(power_ups.collect(&mut pong, id, 0, 0, Side::Left).is_none());
+
+        power_ups.active[0].expires_at = Instant::now();
+        assert_eq!(power_ups.expire_effects(&mut pong).len(), 1);
+        assert_eq!(power_ups.paddle_scale(0), 1.0);
+    }
+}
@@ -0,0 +1,80 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::pong::Pong;
+
+/// Stands in for the opponent in practice, answering every return with a new
+/// ball fired back from near the far wall. Without one the far wall just
+/// bounces the ball back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BallMachine {
+    /// Arena units per second.
+    pub speed: f32,
+    /// Balls are fired up to this many degrees either side of straight at the
+    /// player's wall.
+    pub spread: f32,
+}
+
+impl BallMachine {
+    /// Fires a ball from the right of the arena towards the left wall and
+    /// returns its id.
+    pub fn fire(&self, pong: &mut Pong) -> u32 {
+        let (width, height) = pong.arena_size();
+        let rng = pong.rng();
+        let y = rng.gen_range(height * 0.2..height * 0.8);
+        let angle = rng.gen_range(-self.spread..=self.spread).to_radians();
+        pong.launch_ball(
+            width * 0.9,
+            y,
+            -self.speed * angle.cos(),
+            self.speed * angle.sin(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PracticeStats {
+    pub returns: u32,
+    pub misses: u32,
+    /// Returns since the last miss.
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+impl PracticeStats {
+    pub fn record_return(&mut self) {
+        self.returns += 1;
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    pub fn record_miss(&mut self) {
+        self.misses += 1;
+        self.streak = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn miss_ends_streak_but_keeps_best() {
+        let mut stats = PracticeStats::default();
+        for _ in 0..3 {
+            stats.record_return();
+        }
+        stats.record_miss();
+        stats.record_return();
+
+        assert_eq!(
+            stats,
+            PracticeStats {
+                returns: 4,
+                misses: 1,
+                streak: 1,
+                best_streak: 3,
+            }
+        );
+    }
+}
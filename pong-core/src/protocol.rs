@@ -0,0 +1,398 @@
+//! What the server sends down players' websockets, as JSON with the kind of
+//! message in `event_type` and everything else in `event_body`:
+//!
+//! ```json
+//! {"event_type": "end_point", "event_body": {"p1_score": 2, "p2_score": 1,
+//!   "clock": null, "lives": {"p1": null, "p2": null}}}
+//! ```
+//!
+//! Positions and speeds are in pixels, apart from in `observation` and
+//! `matched`, which only bots get and are in arena units. What clients send
+//! back is in `client_event`. Before connecting, clients join a game and are
+//! told how it is laid out in a `GameConfig`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::GameMode,
+    map::Map,
+    pong::{Orientation, Side},
+    power_up::PowerUpKind,
+    practice::PracticeStats,
+    settings::MatchSettings,
+};
+
+/// Everything a client needs to draw a game and move its paddle, in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub game_id: String,
+    pub player_id: String,
+    pub width: f32,
+    pub height: f32,
+    pub paddle_width: f32,
+    pub ball_radius: f32,
+    /// Pixels per arena unit.
+    pub scaling_factor: f32,
+    /// How far the player's own paddle can move along its wall.
+    pub lane_min: f32,
+    pub lane_max: f32,
+    /// Fastest the player's own paddle may move, in pixels per second.
+    pub max_speed: Option<f32>,
+    pub ball_start: Position,
+    /// Where every paddle in the game starts, keyed by player id.
+    pub paddles: HashMap<String, PaddleStart>,
+    /// Obstacles, in arena units.
+    pub map: Map,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PaddleStart {
+    pub x: f32,
+    pub y: f32,
+    /// Handicaps can make paddles longer or shorter than the default.
+    pub length: f32,
+    pub horizontal: bool,
+}
+
+impl GameConfig {
+    /// How `player_id` sees a game played in `mode` with `settings`.
+    pub fn new(
+        game_id: String,
+        player_id: String,
+        mode: GameMode,
+        settings: &MatchSettings,
+    ) -> Self {
+        let scale = settings.scaling_factor;
+        let (_, side, lane) = mode
+            .slots()
+            .iter()
+            .find(|(id, _, _)| *id == player_id)
+            .expect("no such player");
+        let handicap = settings.handicap(&player_id);
+        let length = settings.player_height * handicap.paddle_scale;
+        let (lane_min, lane_max) = lane.bounds(side.orientation(), length, settings);
+        let (ball_x, ball_y) = settings.ball_start();
+
+        let paddles = mode
+            .slots()
+            .iter()
+            .map(|(id, side, lane)| {
+                let length = settings.player_height * settings.handicap(id).paddle_scale;
+                let (x, y) = side.start(*lane, length, settings);
+                let start = PaddleStart {
+                    x: x * scale,
+                    y: y * scale,
+                    length: length * scale,
+                    horizontal: side.orientation() == Orientation::Horizontal,
+                };
+                (id.to_string(), start)
+            })
+            .collect();
+
+        GameConfig {
+            game_id,
+            player_id,
+            width: settings.width * scale,
+            height: settings.height * scale,
+            paddle_width: settings.player_width * scale,
+            ball_radius: settings.ball_radius * scale,
+            scaling_factor: scale,
+            lane_min: lane_min * scale,
+            lane_max: lane_max * scale,
+            max_speed: handicap.max_speed.map(|s| s * scale),
+            ball_start: Position {
+                x: ball_x * scale,
+                y: ball_y * scale,
+            },
+            paddles,
+            map: settings.map.clone(),
+        }
+    }
+
+    pub fn own_paddle(&self) -> PaddleStart {
+        self.paddles[&self.player_id]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event_type", content = "event_body", rename_all = "snake_case")]
+pub enum ServerEvent {
+    UpdateScreen(UpdateScreen),
+    Observation(Observation),
+    /// A ball bounced off a paddle or a wall, for clients to play a sound or
+    /// show an effect.
+    BallHit(BallHit),
+    /// The last ball in play was scored, everything goes back to the start.
+    EndPoint(Scores),
+    /// A ball was scored but others are still in play.
+    BallScored(BallScored),
+    PracticeStats(PracticeStats),
+    PowerUpSpawned(PowerUpSpawned),
+    PowerUpExpired(PowerUpExpired),
+    PowerUpCollected(PowerUpCollected),
+    PowerUpEnded(PowerUpEnded),
+    GameOver(GameOver),
+    RematchRequested(RematchRequested),
+    RematchStarted(RematchStarted),
+    ActionLate(ActionLate),
+    Matched(Matched),
+    ServerShutdown(ServerShutdown),
+    Announcement(Notice),
+    Kicked(Notice),
+    GameTerminated(Notice),
+}
+
+impl ServerEvent {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("server events always serialise")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Where everything is this tick, sent to human players.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateScreen {
+    pub balls: Vec<BallPosition>,
+    /// Current centres of the map's moving obstacles.
+    pub obstacles: Vec<ObstaclePosition>,
+    /// Top left corner of every paddle, keyed by player id.
+    pub players: HashMap<String, Position>,
+    /// Milliseconds left on the match clock, if the match is timed.
+    pub clock: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BallPosition {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ObstaclePosition {
+    /// Where the obstacle is in the map's list of obstacles.
+    pub index: usize,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Sent to bots every tick in place of `UpdateScreen`, see `bot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub tick: u64,
+    pub balls: Vec<ObservedBall>,
+    pub paddles: HashMap<String, ObservedPaddle>,
+    pub clock: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ObservedBall {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    /// Arena units per second.
+    pub vx: f32,
+    pub vy: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ObservedPaddle {
+    pub x: f32,
+    pub y: f32,
+    pub length: f32,
+    pub side: Side,
+    pub score: u32,
+    pub lives: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BallHit {
+    pub ball_id: u32,
+    /// Whose paddle it hit, if it was a paddle.
+    pub player_id: Option<String>,
+    pub wall: Option<Side>,
+    /// Where along the paddle it hit, from -1 at the top (or left) end to 1
+    /// at the other.
+    pub offset: Option<f32>,
+    pub rally: u32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scores {
+    pub p1_score: u32,
+    pub p2_score: u32,
+    pub clock: Option<u64>,
+    /// Lives left per player, all null unless playing free-for-all.
+    pub lives: HashMap<String, Option<u32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BallScored {
+    pub ball_ids: Vec<u32>,
+    #[serde(flatten)]
+    pub scores: Scores,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpSpawned {
+    pub id: u32,
+    pub kind: PowerUpKind,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// Nobody collected the power-up in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpExpired {
+    pub id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpCollected {
+    pub id: u32,
+    pub kind: PowerUpKind,
+    pub player_id: String,
+    /// How long the effect lasts, null if it doesn't wear off.
+    pub duration_ms: Option<u64>,
+    pub paddle_scale: f32,
+    pub shielded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpEnded {
+    pub kind: PowerUpKind,
+    pub player_id: String,
+    pub paddle_scale: f32,
+    pub shielded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameOver {
+    pub winners: Vec<String>,
+    pub p1_score: u32,
+    pub p2_score: u32,
+    pub clock: Option<u64>,
+    pub stats: StatsSummary,
+}
+
+/// The match's statistics with speeds and distances in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsSummary {
+    pub longest_rally: u32,
+    pub average_rally: f32,
+    pub fastest_ball: f32,
+    pub hits: HashMap<String, u32>,
+    pub points_on_serve: HashMap<String, u32>,
+    pub time_in_play_ms: u64,
+    pub paddle_distance: HashMap<String, f32>,
+}
+
+/// Sent to everyone but the player asking for the rematch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RematchRequested {
+    pub player_id: String,
+    pub swap_sides: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RematchStarted {
+    pub swap_sides: bool,
+    /// Where every paddle starts the rematch, as sides may have been swapped.
+    pub players: HashMap<String, Position>,
+}
+
+/// A bot's action missed its tick and was ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ActionLate {
+    pub tick: u64,
+    pub latest_tick: u64,
+    pub late_actions: u32,
+}
+
+/// The first message a bot gets, telling it where it is playing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Matched {
+    pub game_id: String,
+    pub player_id: String,
+    pub side: Side,
+    pub arena: Arena,
+    pub paddle_width: f32,
+    pub tick_deadline_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerShutdown {
+    pub text: String,
+    /// How long games in progress have to get to where the server is waiting
+    /// for them to.
+    pub deadline_ms: u64,
+}
+
+/// Something for people rather than clients to act on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notice {
+    pub text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_is_in_pixels_and_has_every_paddle() {
+        let settings = MatchSettings::default();
+        let config = GameConfig::new("123".into(), "p2".into(), GameMode::Singles, &settings);
+        let scale = settings.scaling_factor;
+
+        assert_eq!(config.width, settings.width * scale);
+        assert_eq!(config.paddles.len(), 2);
+        let own = config.own_paddle();
+        assert_eq!(own.length, settings.player_height * scale);
+        assert!(!own.horizontal);
+        assert!(own.x > config.paddles["p1"].x);
+        assert!(config.lane_min <= own.y && own.y <= config.lane_max);
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<GameConfig>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn events_keep_their_type_and_body_apart() {
+        let event = ServerEvent::BallScored(BallScored {
+            ball_ids: vec![1],
+            scores: Scores {
+                p1_score: 2,
+                p2_score: 0,
+                clock: None,
+                lives: HashMap::from([(String::from("p1"), None)]),
+            },
+        });
+        let json: serde_json::Value = serde_json::from_str(&event.to_json()).unwrap();
+        assert_eq!(json["event_type"], "ball_scored");
+        assert_eq!(json["event_body"]["p1_score"], 2);
+        assert_eq!(json["event_body"]["ball_ids"][0], 1);
+        assert_eq!(serde_json::from_value::<ServerEvent>(json).unwrap(), event);
+
+        let hit = r#"{"event_type": "ball_hit", "event_body": {"ball_id": 0,
+            "player_id": null, "wall": "top", "offset": null, "rally": 3, "speed": 90}}"#;
+        match serde_json::from_str(hit).unwrap() {
+            ServerEvent::BallHit(hit) => assert_eq!(hit.wall, Some(Side::Top)),
+            _ => panic!("not a ball hit"),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{PLAYER_1, PLAYER_2, PLAYER_3, PLAYER_4, POINTS_TO_WIN},
+    map::{Map, DEFAULT_MAP},
+    player::Handicap,
+    pong::{BALL_RADIUS, PLAYER_HEIGHT, PLAYER_WIDTH, PONG_HEIGHT, PONG_WIDTH},
+    practice::BallMachine,
+};
+
+pub const SCALING_FACTOR: f32 = 8.0;
+pub const MAX_BALLS: usize = 8;
+
+/// How many balls are in play. A point starts with `count` balls and, if
+/// `spawn_every` is set, gains another one at that interval up to `MAX_BALLS`.
+/// Every ball that reaches a goal scores and leaves play; the point is over
+/// once the last one has gone.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BallSettings {
+    pub count: usize,
+    pub spawn_every: Option<Duration>,
+}
+
+impl Default for BallSettings {
+    fn default() -> Self {
+        BallSettings {
+            count: 1,
+            spawn_every: None,
+        }
+    }
+}
+
+/// Everything about a match the host picks when creating it. Sizes are in
+/// arena units; `scaling_factor` turns them into canvas pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchSettings {
+    pub width: f32,
+    pub height: f32,
+    pub player_width: f32,
+    pub player_height: f32,
+    pub ball_radius: f32,
+    pub scaling_factor: f32,
+    pub balls: BallSettings,
+    pub power_ups: bool,
+    /// Timed matches are won by whoever is ahead when time runs out instead
+    /// of by the first to `POINTS_TO_WIN`.
+    pub time_limit: Option<Duration>,
+    pub map: Map,
+    /// Only used in practice.
+    pub ball_machine: Option<BallMachine>,
+    /// Keyed by player id, players without one play unhandicapped.
+    pub handicaps: HashMap<String, Handicap>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            width: PONG_WIDTH,
+            height: PONG_HEIGHT,
+            player_width: PLAYER_WIDTH,
+            player_height: PLAYER_HEIGHT,
+            ball_radius: BALL_RADIUS,
+            scaling_factor: SCALING_FACTOR,
+            balls: BallSettings::default(),
+            power_ups: false,
+            time_limit: None,
+            map: Map::classic(),
+            ball_machine: None,
+            handicaps: HashMap::new(),
+        }
+    }
+}
+
+impl MatchSettings {
+    pub fn ball_start(&self) -> (f32, f32) {
+        (self.width / 2.0, self.height / 2.0)
+    }
+
+    pub fn handicap(&self, player_id: &str) -> Handicap {
+        self.handicaps.get(player_id).copied().unwrap_or_default()
+    }
+
+    /// Builds settings from the create game form, using the defaults for
+    /// anything missing or not recognised. Arenas keep the standard shape and
+    /// are scaled to draw at the same size, maps are stretched to fit.
+    pub fn from_form(form: &HashMap<String, String>) -> Self {
+        let defaults = MatchSettings::default();
+
+        let width = match form.get("arena").map(|a| a.as_str()) {
+            Some("small") => PONG_WIDTH * 0.7,
+            Some("large") => PONG_WIDTH * 1.4,
+            _ => PONG_WIDTH,
+        };
+        let arena_scale = width / PONG_WIDTH;
+
+        let player_height = match form.get("paddle").map(|p| p.as_str()) {
+            Some("short") => PLAYER_HEIGHT * 0.7,
+            Some("long") => PLAYER_HEIGHT * 1.4,
+            _ => PLAYER_HEIGHT,
+        };
+
+        let ball_radius = match form.get("ball").map(|b| b.as_str()) {
+            Some("small") => BALL_RADIUS * 0.7,
+            Some("large") => BALL_RADIUS * 1.5,
+            _ => BALL_RADIUS,
+        };
+
+        let balls = BallSettings {
+            count: form
+                .get("balls")
+                .and_then(|b| b.parse::<usize>().ok())
+                .map_or(1, |b| b.clamp(1, MAX_BALLS)),
+            spawn_every: form
+                .get("spawn_every")
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+        };
+
+        MatchSettings {
+            width,
+            height: PONG_HEIGHT * arena_scale,
+            player_height,
+            ball_radius,
+            scaling_factor: SCALING_FACTOR / arena_scale,
+            balls,
+            power_ups: form.get("power_ups").is_some_and(|p| p == "on"),
+            time_limit: form
+                .get("time_limit")
+                .and_then(|t| t.parse::<u64>().ok())
+                .filter(|t| *t > 0)
+                .map(Duration::from_secs),
+            map: Map::named(form.get("map").map_or(DEFAULT_MAP, |m| m.as_str()))
+                .scaled(arena_scale),
+            ball_machine: ball_machine_from_form(form),
+            handicaps: handicaps_from_form(form),
+            ..defaults
+        }
+    }
+}
+
+/// The ball machine is on with `machine`, firing at `machine_speed` units per
+/// second and up to `machine_spread` degrees off straight.
+fn ball_machine_from_form(form: &HashMap<String, String>) -> Option<BallMachine> {
+    if form.get("machine").map(String::as_str) != Some("on") {
+        return None;
+    }
+    Some(BallMachine {
+        speed: form
+            .get("machine_speed")
+            .and_then(|s| s.parse::<f32>().ok())
+            .filter(|s| s.is_finite())
+            .map_or(50.0, |s| s.clamp(20.0, 100.0)),
+        spread: form
+            .get("machine_spread")
+            .and_then(|s| s.parse::<f32>().ok())
+            .filter(|s| s.is_finite())
+            .map_or(15.0, |s| s.clamp(0.0, 60.0)),
+    })
+}
+
+/// Reads `<player>_paddle_scale`, `<player>_max_speed`, `<player>_head_start`
+/// and `<player>_serves` for every player slot, skipping players left on the
+/// defaults.
+fn handicaps_from_form(form: &HashMap<String, String>) -> HashMap<String, Handicap> {
+    let field = |id: &str, name: &str| form.get(&format!("{}_{}", id, name));
+
+    [PLAYER_1, PLAYER_2, PLAYER_3, PLAYER_4]
+        .into_iter()
+        .filter_map(|id| {
+            let handicap = Handicap {
+                paddle_scale: field(id, "paddle_scale")
+                    .and_then(|s| s.parse::<f32>().ok())
+                    .filter(|s| s.is_finite())
+                    .map_or(1.0, |s| s.clamp(0.5, 2.0)),
+                max_speed: field(id, "max_speed")
+                    .and_then(|s| s.parse::<f32>().ok())
+                    .filter(|s| s.is_finite() && *s > 0.0),
+                head_start: field(id, "head_start")
+                    .and_then(|h| h.parse::<u32>().ok())
+                    .map_or(0, |h| h.min(POINTS_TO_WIN - 1)),
+                serves: field(id, "serves").is_some_and(|s| s == "on"),
+            };
+            (handicap != Handicap::default()).then(|| (String::from(id), handicap))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_arena_draws_at_the_same_size() {
+        let form = HashMap::from([
+            (String::from("arena"), String::from("large")),
+            (String::from("paddle"), String::from("long")),
+            (String::from("balls"), String::from("100")),
+        ]);
+
+        let settings = MatchSettings::from_form(&form);
+        let defaults = MatchSettings::default();
+
+        assert!(settings.width > defaults.width);
+        assert!(settings.player_height > defaults.player_height);
+        assert!(
+            (settings.width * settings.scaling_factor - defaults.width * defaults.scaling_factor)
+                .abs()
+                < 0.01
+        );
+        assert_eq!(settings.balls.count, MAX_BALLS);
+    }
+
+    #[test]
+    fn ball_machine_ignores_values_that_are_not_numbers() {
+        let form = HashMap::from([
+            (String::from("machine"), String::from("on")),
+            (String::from("machine_speed"), String::from("inf")),
+            (String::from("machine_spread"), String::from("NaN")),
+        ]);
+
+        let machine = MatchSettings::from_form(&form).ball_machine.unwrap();
+
+        assert_eq!(machine.speed, 50.0);
+        assert_eq!(machine.spread, 15.0);
+    }
+
+    #[test]
+    fn reads_handicaps_per_player() {
+        let form = HashMap::from([
+            (String::from("p1_paddle_scale"), String::from("1.5")),
+            (String::from("p1_head_start"), String::from("2")),
+            (String::from("p2_max_speed"), String::from("")),
+            (String::from("p3_paddle_scale"), String::from("NaN")),
+        ]);
+
+        let settings = MatchSettings::from_form(&form);
+
+        assert_eq!(settings.handicap(PLAYER_1).paddle_scale, 1.5);
+        assert_eq!(settings.handicap(PLAYER_1).head_start, 2);
+        assert!(!settings.handicaps.contains_key(PLAYER_2));
+        assert!(!settings.handicaps.contains_key(PLAYER_3));
+    }
+}
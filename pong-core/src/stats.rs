@@ -0,0 +1,106 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Running totals for one match, started over for a rematch. Speeds and
+/// distances are in arena units, players are keyed by id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchStats {
+    /// Points played to the end.
+    pub points: u32,
+    pub longest_rally: u32,
+    /// Rallies that have ended with their ball being scored, and the paddle
+    /// hits in them.
+    pub rallies: u32,
+    pub rally_hits: u32,
+    /// Arena units per second.
+    pub fastest_ball: f32,
+    pub hits: HashMap<String, u32>,
+    /// Points scored straight from the serve, before any paddle touched the
+    /// ball.
+    pub points_on_serve: HashMap<String, u32>,
+    pub time_in_play: Duration,
+    pub paddle_distance: HashMap<String, f32>,
+    /// Where each paddle was last step, to measure how far it has moved.
+    #[serde(skip)]
+    paddle_positions: HashMap<String, (f32, f32)>,
+}
+
+impl MatchStats {
+    /// Counts a paddle hit by `player_id` that took its ball's rally to `rally`.
+    pub fn record_hit(&mut self, player_id: &str, rally: u32) {
+        *self.hits.entry(player_id.into()).or_default() += 1;
+        self.longest_rally = self.longest_rally.max(rally);
+    }
+
+    pub fn record_speed(&mut self, speed: f32) {
+        self.fastest_ball = self.fastest_ball.max(speed);
+    }
+
+    pub fn end_rally(&mut self, rally: u32) {
+        self.rallies += 1;
+        self.rally_hits += rally;
+    }
+
+    pub fn record_point_on_serve(&mut self, player_id: &str) {
+        *self.points_on_serve.entry(player_id.into()).or_default() += 1;
+    }
+
+    /// Adds the distance from where `player_id`'s paddle was last step.
+    pub fn move_paddle(&mut self, player_id: &str, x: f32, y: f32) {
+        if let Some((last_x, last_y)) = self.paddle_positions.insert(player_id.into(), (x, y)) {
+            *self.paddle_distance.entry(player_id.into()).or_default() +=
+                (x - last_x).hypot(y - last_y);
+        }
+    }
+
+    /// Paddles are put back at the start of every point, which isn't the
+    /// players moving them.
+    pub fn forget_paddles(&mut self) {
+        self.paddle_positions.clear();
+    }
+
+    /// Paddle hits per finished rally, zero before any have finished.
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies == 0 {
+            0.0
+        } else {
+            self.rally_hits as f32 / self.rallies as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paddles_put_back_for_a_new_point_have_not_travelled() {
+        let mut stats = MatchStats::default();
+        stats.move_paddle("p1", 0.0, 10.0);
+        stats.move_paddle("p1", 0.0, 13.0);
+        stats.move_paddle("p1", 0.0, 9.0);
+        stats.forget_paddles();
+        stats.move_paddle("p1", 0.0, 30.0);
+        stats.move_paddle("p1", 0.0, 31.0);
+
+        assert_eq!(stats.paddle_distance["p1"], 8.0);
+    }
+
+    #[test]
+    fn average_rally_only_counts_finished_rallies() {
+        let mut stats = MatchStats::default();
+        assert_eq!(stats.average_rally(), 0.0);
+
+        for rally in 1..=4 {
+            stats.record_hit(if rally % 2 == 1 { "p1" } else { "p2" }, rally);
+        }
+        stats.end_rally(4);
+        stats.end_rally(0);
+        stats.record_hit("p1", 7);
+
+        assert_eq!(stats.average_rally(), 2.0);
+        assert_eq!(stats.longest_rally, 7);
+        assert_eq!(stats.hits["p1"], 3);
+    }
+}
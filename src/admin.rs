@@ -0,0 +1,183 @@
+//! Lets operators see and control live games at `/admin?token=<token>`, or as
+//! JSON at `/admin/games?token=<token>`. The token is set in the
+//! `PONG_ADMIN_TOKEN` environment variable, and nobody can get in if it isn't.
+//! It is passed around in links and forms, so keep it to letters and digits.
+
+use std::{collections::HashMap, env, str::FromStr};
+
+use lazy_static::lazy_static;
+use pong_core::protocol::ServerEvent;
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::info;
+use warp::filters::ws::Message;
+
+use crate::{
+    game::{self, Game},
+    templates, CONNECTION_STORE, GAME_STORE,
+};
+
+lazy_static! {
+    static ref ADMIN_TOKEN: Option<String> =
+        env::var("PONG_ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+}
+
+pub fn is_authorised(token: Option<&str>) -> bool {
+    token.is_some() && token == ADMIN_TOKEN.as_deref()
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerSummary {
+    pub id: String,
+    pub score: u32,
+    pub lives: Option<u32>,
+    pub bot: bool,
+    pub connected: bool,
+    pub kicked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GameSummary {
+    pub id: String,
+    pub mode: String,
+    pub status: String,
+    pub uptime_secs: u64,
+    pub players: Vec<PlayerSummary>,
+}
+
+/// Every game in the store, oldest first.
+pub async fn games() -> Vec<GameSummary> {
+    let games = GAME_STORE.read().await;
+    let connections = CONNECTION_STORE.read().await;
+    let mut summaries: Vec<_> = games
+        .values()
+        .map(|game| summary(game, connections.get(&game.id)))
+        .collect();
+    summaries.sort_by_key(|s| std::cmp::Reverse(s.uptime_secs));
+    summaries
+}
+
+fn summary(
+    game: &Game,
+    connections: Option<&HashMap<String, UnboundedSender<Message>>>,
+) -> GameSummary {
+    let players = game
+        .players()
+        .into_iter()
+        .map(|(id, player)| PlayerSummary {
+            id: id.to_string(),
+            score: player.score,
+            lives: player.lives,
+            bot: player.bot,
+            connected: connections
+                .and_then(|c| c.get(id))
+                .is_some_and(|tx| !tx.is_closed()),
+            kicked: player.kicked,
+        })
+        .collect();
+    GameSummary {
+        id: game.id.clone(),
+        mode: format!("{:?}", game.mode),
+        status: templates::game_status(game),
+        uptime_secs: game.created_at.elapsed().as_secs(),
+        players,
+    }
+}
+
+/// Removes a game, which stops it being played, and closes its players'
+/// websockets. Unfinished tournament and league games are replayed in a new
+/// game. Returns false if there was no such game.
+pub async fn terminate(game_id: &str) -> bool {
+    let removed = game::remove(game_id, "This game was ended by the server").await;
+    info!(game_id, removed, "terminated game");
+    removed
+}
+
+/// What came of kicking a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kick {
+    NoSuchGame,
+    NoSuchPlayer,
+    /// The game hadn't started, so someone else can take their slot.
+    Freed,
+    /// The game is under way, so they keep their slot but can't reconnect.
+    KeptOut,
+}
+
+impl Kick {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kick::NoSuchGame => "no_such_game",
+            Kick::NoSuchPlayer => "no_such_player",
+            Kick::Freed => "freed",
+            Kick::KeptOut => "kept_out",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Kick::NoSuchGame => "There is no such game any more.",
+            Kick::NoSuchPlayer => "That player isn't in the game.",
+            Kick::Freed => "The player was kicked and their slot freed.",
+            Kick::KeptOut => "The player was kicked and can't rejoin the game in progress.",
+        }
+    }
+}
+
+impl FromStr for Kick {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "no_such_game" => Ok(Kick::NoSuchGame),
+            "no_such_player" => Ok(Kick::NoSuchPlayer),
+            "freed" => Ok(Kick::Freed),
+            "kept_out" => Ok(Kick::KeptOut),
+            _ => Err(format!("unknown kick: {}", s)),
+        }
+    }
+}
+
+/// Closes a player's websocket and frees their slot if the game hasn't
+/// started, or keeps them from reconnecting if it has.
+pub async fn kick(game_id: &str, player_id: &str) -> Kick {
+    let mut games = GAME_STORE.write().await;
+    let Some(game) = games.get_mut(game_id) else {
+        return Kick::NoSuchGame;
+    };
+    let kick = if game.remove_player(player_id) {
+        Kick::Freed
+    } else if game.kick_player(player_id) {
+        Kick::KeptOut
+    } else {
+        return Kick::NoSuchPlayer;
+    };
+    let connection = CONNECTION_STORE
+        .write()
+        .await
+        .get_mut(game_id)
+        .and_then(|players| players.remove(player_id));
+    if let Some(tx) = connection {
+        let _ = tx.send(game::notice(
+            ServerEvent::Kicked,
+            "You were removed from this game",
+        ));
+        let _ = tx.send(Message::close());
+    }
+    info!(game_id, player_id, kick = kick.name(), "kicked player");
+    kick
+}
+
+/// Sends `text` to everyone connected. Returns how many websockets it went to.
+pub async fn announce(text: &str) -> usize {
+    let message = game::notice(ServerEvent::Announcement, text);
+    let sent = CONNECTION_STORE
+        .read()
+        .await
+        .values()
+        .flat_map(|players| players.values())
+        .filter(|tx| tx.send(message.clone()).is_ok())
+        .count();
+    info!(text, sent, "announcement");
+    sent
+}
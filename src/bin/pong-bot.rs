@@ -0,0 +1,385 @@
+//! Plays lots of games against a running server at once to see how it holds
+//! up. Every game gets two bots that join both slots, ready up and track the
+//! nearest ball with their paddle, asking for a rematch whenever a game ends.
+//!
+//! ```sh
+//! cargo run --release --bin pong-bot -- --games 50 --duration 60 --server 127.0.0.1:3030
+//! ```
+
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use hyper::{body, client::HttpConnector, header, Body, Client, Method, Request};
+use pong_core::protocol::{GameConfig, ServerEvent, UpdateScreen};
+use serde_json::{json, Value};
+use tokio::time::{interval, sleep_until};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const PLAYERS: [&str; 2] = ["p1", "p2"];
+/// Pixels a bot moves its paddle per update, the same as the browser client
+/// does per frame.
+const PADDLE_SPEED: f32 = 8.0;
+const REPORT_EVERY: Duration = Duration::from_secs(5);
+
+struct Args {
+    games: usize,
+    duration: Duration,
+    server: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        games: 10,
+        duration: Duration::from_secs(30),
+        server: String::from("127.0.0.1:3030"),
+    };
+
+    let mut argv = env::args().skip(1);
+    while let Some(flag) = argv.next() {
+        let value = argv
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--games" => args.games = value.parse().map_err(|_| "--games must be a number")?,
+            "--duration" => {
+                args.duration =
+                    Duration::from_secs(value.parse().map_err(|_| "--duration must be seconds")?)
+            }
+            "--server" => args.server = value,
+            _ => return Err(format!("unknown argument {}", flag)),
+        }
+    }
+    Ok(args)
+}
+
+#[derive(Default)]
+struct Stats {
+    messages: u64,
+    moves_sent: u64,
+    games_finished: u64,
+    errors: u64,
+    /// Time between consecutive `update_screen` messages on one connection.
+    update_gaps: Vec<Duration>,
+    /// Websocket ping round trips.
+    pings: Vec<Duration>,
+}
+
+type SharedStats = Arc<Mutex<Stats>>;
+
+fn record_error(stats: &SharedStats, err: impl std::fmt::Display) {
+    eprintln!("error: {}", err);
+    stats.lock().unwrap().errors += 1;
+}
+
+/// Creates a singles game and returns its id.
+async fn create_game(client: &Client<HttpConnector>, server: &str) -> Result<String, String> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://{}/create_game", server))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("name=bot&mode=singles"))
+        .map_err(|e| e.to_string())?;
+    let response = client.request(request).await.map_err(|e| e.to_string())?;
+
+    response
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .and_then(|location| location.strip_prefix("/games/"))
+        .map(String::from)
+        .ok_or_else(|| format!("create_game answered {}", response.status()))
+}
+
+/// Takes `player`'s slot in the game and returns how the game is laid out.
+async fn join(
+    client: &Client<HttpConnector>,
+    server: &str,
+    game_id: &str,
+    player: &str,
+) -> Result<GameConfig, String> {
+    let uri = format!("http://{}/games/{}/join?player={}", server, game_id, player)
+        .parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| e.to_string())?;
+    let response = client.get(uri).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "joining {} answered {}",
+            game_id,
+            response.status()
+        ));
+    }
+    let config = body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&config).map_err(|e| e.to_string())
+}
+
+/// Where a paddle `paddle_length` long wants to be to meet the ball nearest
+/// to its wall, or `None` if there are no balls.
+fn target(update: &UpdateScreen, player: &str, paddle_length: f32) -> Option<f32> {
+    let nearest = update.balls.iter().min_by(|a, b| {
+        if player == PLAYERS[0] {
+            a.x.total_cmp(&b.x)
+        } else {
+            b.x.total_cmp(&a.x)
+        }
+    })?;
+    Some(nearest.y - paddle_length / 2.0)
+}
+
+/// Plays as `player` until `deadline`.
+async fn play(
+    server: String,
+    game_id: String,
+    player: &'static str,
+    paddle_length: f32,
+    stats: SharedStats,
+    deadline: Instant,
+) -> Result<(), String> {
+    let (socket, _) = connect_async(format!("ws://{}/ws/{}/{}", server, game_id, player))
+        .await
+        .map_err(|e| e.to_string())?;
+    let (mut tx, mut rx) = socket.split();
+
+    let send = |value: Value| Message::Text(value.to_string());
+    tx.send(send(
+        json!({ "event_type": "ready", "event_body": { "player_id": player } }),
+    ))
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut pings = interval(Duration::from_secs(1));
+    let mut ping_sent: Option<Instant> = None;
+    let mut last_update: Option<Instant> = None;
+    let deadline = tokio::time::Instant::from_std(deadline);
+
+    loop {
+        let message = tokio::select! {
+            _ = sleep_until(deadline) => break,
+            _ = pings.tick() => {
+                ping_sent = Some(Instant::now());
+                tx.send(Message::Ping(vec![])).await.map_err(|e| e.to_string())?;
+                continue;
+            }
+            message = rx.next() => match message {
+                Some(message) => message.map_err(|e| e.to_string())?,
+                None => return Err(format!("{} {} disconnected", game_id, player)),
+            },
+        };
+
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Pong(_) => {
+                if let Some(sent) = ping_sent.take() {
+                    stats.lock().unwrap().pings.push(sent.elapsed());
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        stats.lock().unwrap().messages += 1;
+
+        let event: ServerEvent = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let reply = match event {
+            ServerEvent::UpdateScreen(update) => {
+                let now = Instant::now();
+                if let Some(last) = last_update.replace(now) {
+                    stats.lock().unwrap().update_gaps.push(now - last);
+                }
+
+                let current = update.players.get(player).map(|p| p.y);
+                match (current, target(&update, player, paddle_length)) {
+                    (Some(current), Some(target)) if (target - current).abs() > 1.0 => {
+                        let y = current + (target - current).clamp(-PADDLE_SPEED, PADDLE_SPEED);
+                        stats.lock().unwrap().moves_sent += 1;
+                        Some(json!({ "event_type": "move", "event_body": { "y": y } }))
+                    }
+                    _ => None,
+                }
+            }
+            ServerEvent::GameOver(_) => {
+                if player == PLAYERS[0] {
+                    stats.lock().unwrap().games_finished += 1;
+                    Some(json!({
+                        "event_type": "rematch_request",
+                        "event_body": { "swap_sides": false },
+                    }))
+                } else {
+                    None
+                }
+            }
+            ServerEvent::RematchRequested(_) => {
+                Some(json!({ "event_type": "rematch_accept", "event_body": {} }))
+            }
+            _ => None,
+        };
+
+        if let Some(reply) = reply {
+            tx.send(send(reply)).await.map_err(|e| e.to_string())?;
+        }
+    }
+
+    let _ = tx.send(Message::Close(None)).await;
+    Ok(())
+}
+
+async fn start_game(
+    client: Client<HttpConnector>,
+    server: String,
+    stats: SharedStats,
+    deadline: Instant,
+) {
+    let game_id = match create_game(&client, &server).await {
+        Ok(game_id) => game_id,
+        Err(err) => return record_error(&stats, err),
+    };
+
+    let mut bots = vec![];
+    for player in PLAYERS {
+        match join(&client, &server, &game_id, player).await {
+            Ok(config) => bots.push(tokio::spawn(play(
+                server.clone(),
+                game_id.clone(),
+                player,
+                config.own_paddle().length,
+                stats.clone(),
+                deadline,
+            ))),
+            Err(err) => return record_error(&stats, err),
+        }
+    }
+
+    for bot in bots {
+        match bot.await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => record_error(&stats, err),
+            Err(err) => record_error(&stats, err),
+        }
+    }
+}
+
+fn percentiles(samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return String::from("no samples");
+    }
+    samples.sort();
+    let at = |p: f64| {
+        let i = ((samples.len() - 1) as f64 * p).round() as usize;
+        samples[i].as_secs_f64() * 1000.0
+    };
+    format!(
+        "p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        at(1.0)
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("usage: pong-bot [--games N] [--duration SECONDS] [--server HOST:PORT]");
+            std::process::exit(2);
+        }
+    };
+
+    let client = Client::new();
+    let stats = SharedStats::default();
+    let started = Instant::now();
+    let deadline = started + args.duration;
+
+    println!(
+        "playing {} games against {} for {}s",
+        args.games,
+        args.server,
+        args.duration.as_secs()
+    );
+    let games: Vec<_> = (0..args.games)
+        .map(|_| {
+            tokio::spawn(start_game(
+                client.clone(),
+                args.server.clone(),
+                stats.clone(),
+                deadline,
+            ))
+        })
+        .collect();
+
+    let reporter = tokio::spawn({
+        let stats = stats.clone();
+        async move {
+            let mut last_messages = 0;
+            let mut reports = interval(REPORT_EVERY);
+            reports.tick().await;
+            loop {
+                reports.tick().await;
+                let stats = stats.lock().unwrap();
+                println!(
+                    "{:>4}s  {:.0} messages/s  {} errors",
+                    started.elapsed().as_secs(),
+                    (stats.messages - last_messages) as f64 / REPORT_EVERY.as_secs_f64(),
+                    stats.errors
+                );
+                last_messages = stats.messages;
+            }
+        }
+    });
+
+    for game in games {
+        if let Err(err) = game.await {
+            record_error(&stats, err);
+        }
+    }
+    reporter.abort();
+
+    let elapsed = started.elapsed().as_secs_f64();
+    let mut stats = stats.lock().unwrap();
+    println!();
+    println!("games finished  {}", stats.games_finished);
+    println!(
+        "messages        {} ({:.0}/s)",
+        stats.messages,
+        stats.messages as f64 / elapsed
+    );
+    println!(
+        "moves sent      {} ({:.0}/s)",
+        stats.moves_sent,
+        stats.moves_sent as f64 / elapsed
+    );
+    println!("update gaps     {}", percentiles(&mut stats.update_gaps));
+    println!("ping round trip {}", percentiles(&mut stats.pings));
+    println!("errors          {}", stats.errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bots_track_the_ball_nearest_their_wall() {
+        let update: UpdateScreen = serde_json::from_value(json!({
+            "balls": [{ "id": 0, "x": 100, "y": 50 }, { "id": 1, "x": 700, "y": 300 }],
+            "obstacles": [],
+            "players": { "p1": { "x": 0, "y": 0 }, "p2": { "x": 790, "y": 0 } },
+            "clock": null,
+        }))
+        .unwrap();
+
+        assert_eq!(target(&update, "p1", 40.0), Some(30.0));
+        assert_eq!(target(&update, "p2", 40.0), Some(280.0));
+
+        let no_balls = UpdateScreen {
+            balls: vec![],
+            ..update
+        };
+        assert_eq!(target(&no_balls, "p1", 40.0), None);
+    }
+}
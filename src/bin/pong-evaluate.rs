@@ -0,0 +1,22 @@
+//! Plays the baseline AI against a random paddle in the offline environment
+//! and says how it did, as a yardstick for opponents trained against it.
+//!
+//! ```sh
+//! cargo run --release --bin pong-evaluate -- 1000
+//! ```
+
+use std::env;
+
+use pong_core::environment;
+
+fn main() {
+    let episodes = match env::args().nth(1).map(|n| n.parse()) {
+        Some(Ok(episodes)) => episodes,
+        Some(Err(_)) => {
+            eprintln!("usage: pong-evaluate [episodes]");
+            std::process::exit(2);
+        }
+        None => 100,
+    };
+    println!("{}", environment::evaluate(episodes));
+}
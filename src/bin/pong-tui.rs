@@ -0,0 +1,676 @@
+//! Plays Pong in a terminal. Give it the link to a game, the same one you'd
+//! open or share in a browser, and it takes a slot in that game. A bare game
+//! id joins a game on a server running locally.
+//!
+//! ```sh
+//! cargo run --bin pong-tui -- http://127.0.0.1:3030/games/<game id>?player=p2
+//! ```
+//!
+//! The arrow keys move the paddle, space says you're ready, `r` asks for a
+//! rematch (`s` with sides swapped), `a` accepts one and `q` quits.
+
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures_util::{SinkExt, StreamExt};
+use hyper::{body, Client};
+use pong_core::{
+    map::Shape,
+    power_up::PowerUpKind,
+    practice::PracticeStats,
+    protocol::{GameConfig, Scores, ServerEvent},
+};
+use serde_json::{json, Value};
+use tokio::time::interval;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const DEFAULT_SERVER: &str = "127.0.0.1:3030";
+const FRAME: Duration = Duration::from_millis(33);
+/// Pixels the paddle moves per key press. Terminals repeat held keys about
+/// half as often as the browser client draws frames, so this is twice its step.
+const STEP: f32 = 16.0;
+const HELP: &str =
+    "arrows move  space ready  r rematch  s rematch swapping sides  a accept  q quit";
+
+/// Splits a game link into the server and the path to join the game at.
+fn parse_link(link: &str) -> (String, String) {
+    let link = link.trim_start_matches("http://");
+    let (server, game) = link.split_once("/games/").unwrap_or((DEFAULT_SERVER, link));
+    let join = match game.split_once('?') {
+        Some((id, query)) => format!("/games/{}/join?{}", id, query),
+        None => format!("/games/{}/join", game),
+    };
+    (server.to_string(), join)
+}
+
+/// Takes a slot in the game and returns how it is laid out.
+async fn join(server: &str, path: &str) -> Result<GameConfig, String> {
+    let uri = format!("http://{}{}", server, path)
+        .parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| e.to_string())?;
+    let response = Client::new().get(uri).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(String::from("no such game or it is already full"));
+    }
+    let config = body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| e.to_string())?;
+    serde_json::from_slice(&config).map_err(|e| e.to_string())
+}
+
+/// Everything on screen, kept up to date from the server's messages.
+struct State {
+    config: GameConfig,
+    /// Top left corner of every paddle.
+    paddles: HashMap<String, (f32, f32)>,
+    balls: Vec<(f32, f32)>,
+    /// Current centres of moving obstacles, keyed by their index in the map.
+    obstacles: HashMap<usize, (f32, f32)>,
+    power_ups: HashMap<u32, (char, f32, f32)>,
+    paddle_scales: HashMap<String, f32>,
+    shields: HashMap<String, bool>,
+    score: (u32, u32),
+    /// Remaining lives per player, only sent in free-for-all.
+    lives: HashMap<String, u32>,
+    /// Milliseconds left in timed matches.
+    clock: Option<u64>,
+    /// Return streaks, only sent in practice.
+    practice: Option<PracticeStats>,
+    message: String,
+    last_move: Option<Instant>,
+}
+
+impl State {
+    fn new(config: GameConfig) -> Self {
+        let mut state = State {
+            config,
+            paddles: HashMap::new(),
+            balls: vec![],
+            obstacles: HashMap::new(),
+            power_ups: HashMap::new(),
+            paddle_scales: HashMap::new(),
+            shields: HashMap::new(),
+            score: (0, 0),
+            lives: HashMap::new(),
+            clock: None,
+            practice: None,
+            message: String::from("press space when you're ready"),
+            last_move: None,
+        };
+        state.reset_positions();
+        state
+    }
+
+    fn reset_positions(&mut self) {
+        self.paddles = self
+            .config
+            .paddles
+            .iter()
+            .map(|(id, start)| (id.clone(), (start.x, start.y)))
+            .collect();
+        self.balls = vec![(self.config.ball_start.x, self.config.ball_start.y)];
+        self.power_ups.clear();
+        self.paddle_scales.clear();
+        self.shields.clear();
+    }
+
+    fn scores(&mut self, scores: &Scores) {
+        self.score = (scores.p1_score, scores.p2_score);
+        // only free-for-all players have lives
+        self.lives = scores
+            .lives
+            .iter()
+            .filter_map(|(id, lives)| Some((id.clone(), (*lives)?)))
+            .collect();
+        self.clock = scores.clock;
+    }
+
+    fn handle(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::UpdateScreen(update) => {
+                self.message.clear();
+                self.clock = update.clock;
+                self.balls = update.balls.iter().map(|b| (b.x, b.y)).collect();
+                for obstacle in update.obstacles {
+                    self.obstacles
+                        .insert(obstacle.index, (obstacle.x, obstacle.y));
+                }
+                // our own paddle is moved as soon as a key is pressed
+                for (id, pos) in update.players {
+                    if id != self.config.player_id {
+                        self.paddles.insert(id, (pos.x, pos.y));
+                    }
+                }
+            }
+            ServerEvent::EndPoint(scores) => {
+                self.scores(&scores);
+                self.reset_positions();
+            }
+            ServerEvent::BallScored(scored) => self.scores(&scored.scores),
+            ServerEvent::PowerUpSpawned(power_up) => {
+                let label = match power_up.kind {
+                    PowerUpKind::PaddleGrow => '+',
+                    PowerUpKind::PaddleShrink => '-',
+                    PowerUpKind::BallSpeedUp => '>',
+                    PowerUpKind::BallSlowDown => '<',
+                    PowerUpKind::BallCurve => '~',
+                    PowerUpKind::Shield => 'S',
+                };
+                self.power_ups
+                    .insert(power_up.id, (label, power_up.x, power_up.y));
+            }
+            ServerEvent::PowerUpExpired(expired) => {
+                self.power_ups.remove(&expired.id);
+            }
+            ServerEvent::PowerUpCollected(collected) => {
+                self.power_ups.remove(&collected.id);
+                self.paddle_scales
+                    .insert(collected.player_id.clone(), collected.paddle_scale);
+                self.shields.insert(collected.player_id, collected.shielded);
+            }
+            ServerEvent::PowerUpEnded(ended) => {
+                self.paddle_scales
+                    .insert(ended.player_id.clone(), ended.paddle_scale);
+                self.shields.insert(ended.player_id, ended.shielded);
+            }
+            ServerEvent::PracticeStats(stats) => self.practice = Some(stats),
+            ServerEvent::GameOver(over) => {
+                self.score = (over.p1_score, over.p2_score);
+                self.clock = None;
+                let won = over.winners.contains(&self.config.player_id);
+                self.message = format!(
+                    "{} r for a rematch, s to swap sides",
+                    if won { "You win!" } else { "You lose!" }
+                );
+            }
+            ServerEvent::RematchRequested(requested) => {
+                self.message = if requested.swap_sides {
+                    String::from("Opponent wants a rematch with sides swapped, a to accept")
+                } else {
+                    String::from("Opponent wants a rematch, a to accept")
+                };
+            }
+            ServerEvent::RematchStarted(started) => {
+                for (id, start) in started.players {
+                    if let Some(paddle) = self.config.paddles.get_mut(&id) {
+                        paddle.x = start.x;
+                        paddle.y = start.y;
+                    }
+                }
+                self.score = (0, 0);
+                self.lives.clear();
+                self.message = String::from("press space when you're ready");
+                self.reset_positions();
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves our paddle along its lane, held back to the same top speed the
+    /// server allows. Returns the move to send if the paddle moved.
+    fn move_by(&mut self, delta: f32) -> Option<Value> {
+        let now = Instant::now();
+        let delta = match (self.config.max_speed, self.last_move) {
+            (Some(max_speed), Some(last)) => {
+                let step = max_speed * (now - last).as_secs_f32();
+                delta.clamp(-step, step)
+            }
+            _ => delta,
+        };
+        self.last_move = Some(now);
+
+        let horizontal = self.config.own_paddle().horizontal;
+        let (lane_min, lane_max) = (self.config.lane_min, self.config.lane_max);
+        let paddle = self.paddles.get_mut(&self.config.player_id)?;
+        let (axis, pos) = if horizontal {
+            ("x", &mut paddle.0)
+        } else {
+            ("y", &mut paddle.1)
+        };
+        let moved = (*pos + delta).clamp(lane_min, lane_max);
+        if moved == *pos {
+            return None;
+        }
+        *pos = moved;
+        Some(json!({ "event_type": "move", "event_body": { axis: moved } }))
+    }
+
+    fn status(&self) -> String {
+        let mut status = match self.practice {
+            Some(stats) => format!("streak {}  best {}", stats.streak, stats.best_streak),
+            None if !self.lives.is_empty() => {
+                let mut lives: Vec<_> = self.lives.iter().collect();
+                lives.sort();
+                lives
+                    .iter()
+                    .map(|(id, left)| format!("{}: {}", id, left))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            }
+            None => format!("{}:{}", self.score.0, self.score.1),
+        };
+        match self.clock {
+            Some(0) => status.push_str("  SUDDEN DEATH"),
+            Some(ms) => {
+                let seconds = ms.div_ceil(1000);
+                status.push_str(&format!("  {}:{:02}", seconds / 60, seconds % 60));
+            }
+            None => {}
+        }
+        format!(
+            "{}  (you are {})  {}",
+            status, self.config.player_id, self.message
+        )
+    }
+}
+
+/// The arena drawn as terminal cells, each standing for a rectangle of pixels.
+struct Canvas {
+    cells: Vec<Vec<char>>,
+    cell_width: f32,
+    cell_height: f32,
+}
+
+impl Canvas {
+    fn new(columns: usize, rows: usize, width: f32, height: f32) -> Self {
+        Canvas {
+            cells: vec![vec![' '; columns]; rows],
+            cell_width: width / columns as f32,
+            cell_height: height / rows as f32,
+        }
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (usize, usize) {
+        let rows = self.cells.len();
+        let columns = self.cells[0].len();
+        (
+            ((x / self.cell_width).max(0.0) as usize).min(columns - 1),
+            ((y / self.cell_height).max(0.0) as usize).min(rows - 1),
+        )
+    }
+
+    fn put(&mut self, x: f32, y: f32, c: char) {
+        let (column, row) = self.cell(x, y);
+        self.cells[row][column] = c;
+    }
+
+    /// Fills every cell the rectangle touches, at least one.
+    fn fill(&mut self, x: f32, y: f32, width: f32, height: f32, c: char) {
+        let (left, top) = self.cell(x, y);
+        let (right, bottom) = self.cell(x + width - 0.01, y + height - 0.01);
+        for row in &mut self.cells[top..=bottom.max(top)] {
+            for cell in &mut row[left..=right.max(left)] {
+                *cell = c;
+            }
+        }
+    }
+
+    /// Fills the cells whose centres fall inside `contains`, which is given
+    /// pixel coordinates.
+    fn fill_shape(&mut self, contains: impl Fn(f32, f32) -> bool, c: char) {
+        let (cell_width, cell_height) = (self.cell_width, self.cell_height);
+        for (row, cells) in self.cells.iter_mut().enumerate() {
+            for (column, cell) in cells.iter_mut().enumerate() {
+                let x = (column as f32 + 0.5) * cell_width;
+                let y = (row as f32 + 0.5) * cell_height;
+                if contains(x, y) {
+                    *cell = c;
+                }
+            }
+        }
+    }
+}
+
+fn draw(state: &State, out: &mut impl Write) -> io::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let (columns, rows) = (columns as usize, rows as usize);
+    // a status line, a border all round and the help line
+    if columns < 10 || rows < 6 {
+        return queue!(out, Clear(ClearType::All), MoveTo(0, 0), Print("too small"));
+    }
+    let canvas = render(state, columns - 2, rows - 4);
+
+    let border = "─".repeat(columns - 2);
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        Print(truncate(&state.status(), columns)),
+        MoveTo(0, 1),
+        Print(format!("┌{}┐", border))
+    )?;
+    for (i, row) in canvas.cells.iter().enumerate() {
+        let line: String = row.iter().collect();
+        queue!(out, MoveTo(0, i as u16 + 2), Print(format!("│{}│", line)))?;
+    }
+    queue!(
+        out,
+        MoveTo(0, rows as u16 - 2),
+        Print(format!("└{}┘", border)),
+        MoveTo(0, rows as u16 - 1),
+        Clear(ClearType::CurrentLine),
+        Print(truncate(HELP, columns))
+    )?;
+    out.flush()
+}
+
+/// The arena as it stands, `columns` by `rows` cells.
+fn render(state: &State, columns: usize, rows: usize) -> Canvas {
+    let config = &state.config;
+    let mut canvas = Canvas::new(columns, rows, config.width, config.height);
+    let scale = config.scaling_factor;
+
+    for (index, obstacle) in config.map.obstacles.iter().enumerate() {
+        let (x, y) = state
+            .obstacles
+            .get(&index)
+            .copied()
+            .unwrap_or((obstacle.x * scale, obstacle.y * scale));
+        match &obstacle.shape {
+            Shape::Cuboid {
+                half_width,
+                half_height,
+            } => {
+                let (half_width, half_height) = (half_width * scale, half_height * scale);
+                canvas.fill(
+                    x - half_width,
+                    y - half_height,
+                    2.0 * half_width,
+                    2.0 * half_height,
+                    '#',
+                );
+            }
+            Shape::Ball { radius } => {
+                let radius = radius * scale;
+                canvas.fill_shape(|px, py| (px - x).hypot(py - y) <= radius, '#');
+                canvas.put(x, y, '#');
+            }
+            Shape::ConvexPolygon { points } => {
+                let points: Vec<(f32, f32)> = points
+                    .iter()
+                    .map(|(px, py)| (x + px * scale, y + py * scale))
+                    .collect();
+                // inside if on the same side of every edge
+                let side = |px: f32, py: f32, (ax, ay): (f32, f32), (bx, by): (f32, f32)| {
+                    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+                };
+                canvas.fill_shape(
+                    |px, py| {
+                        let sides: Vec<f32> = (0..points.len())
+                            .map(|i| side(px, py, points[i], points[(i + 1) % points.len()]))
+                            .collect();
+                        !sides.is_empty()
+                            && (sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0))
+                    },
+                    '#',
+                );
+                canvas.put(x, y, '#');
+            }
+        }
+    }
+
+    for (id, (x, y)) in &state.paddles {
+        let start = config.paddles[id];
+        let c = if *id == config.player_id {
+            '█'
+        } else {
+            '▓'
+        };
+        // eliminated players' walls are closed off, shielded ones get a
+        // wall behind the paddle
+        let eliminated = state.lives.get(id) == Some(&0);
+        if eliminated || state.shields.get(id) == Some(&true) {
+            let wall = if eliminated { '░' } else { '·' };
+            if start.horizontal {
+                canvas.fill(0.0, *y, config.width, config.paddle_width, wall);
+            } else {
+                canvas.fill(*x, 0.0, config.paddle_width, config.height, wall);
+            }
+        }
+        if eliminated {
+            continue;
+        }
+        let length = start.length * state.paddle_scales.get(id).unwrap_or(&1.0);
+        let offset = (start.length - length) / 2.0;
+        if start.horizontal {
+            canvas.fill(x + offset, *y, length, config.paddle_width, c);
+        } else {
+            canvas.fill(*x, y + offset, config.paddle_width, length, c);
+        }
+    }
+
+    for (label, x, y) in state.power_ups.values() {
+        canvas.put(*x, *y, *label);
+    }
+    for (x, y) in &state.balls {
+        canvas.put(*x, *y, 'O');
+    }
+    canvas
+}
+
+fn truncate(text: &str, columns: usize) -> String {
+    text.chars().take(columns).collect()
+}
+
+/// Puts the terminal into raw mode on an alternate screen until dropped.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+async fn play(server: &str, config: GameConfig) -> Result<(), String> {
+    let (socket, _) = connect_async(format!(
+        "ws://{}/ws/{}/{}",
+        server, config.game_id, config.player_id
+    ))
+    .await
+    .map_err(|e| e.to_string())?;
+    let (mut tx, mut rx) = socket.split();
+
+    let _terminal = Terminal::enter().map_err(|e| e.to_string())?;
+    let mut state = State::new(config);
+    let mut keys = EventStream::new();
+    let mut frames = interval(FRAME);
+    let mut out = stdout();
+
+    loop {
+        let outgoing = tokio::select! {
+            _ = frames.tick() => {
+                draw(&state, &mut out).map_err(|e| e.to_string())?;
+                None
+            }
+            message = rx.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let event = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+                    state.handle(event);
+                    None
+                }
+                Some(Ok(_)) => None,
+                Some(Err(err)) => return Err(err.to_string()),
+                None => return Err(String::from("the server closed the connection")),
+            },
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) => match on_key(&mut state, key) {
+                    Some(event) => Some(event),
+                    None if is_quit(key) => break,
+                    None => None,
+                },
+                Some(Ok(Event::Resize(_, _))) => {
+                    execute!(out, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+                    None
+                }
+                Some(Ok(_)) => None,
+                Some(Err(err)) => return Err(err.to_string()),
+                None => break,
+            },
+        };
+
+        if let Some(event) = outgoing {
+            tx.send(Message::Text(event.to_string()))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let _ = tx.send(Message::Close(None)).await;
+    Ok(())
+}
+
+fn is_quit(key: KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// The message to send for a key press, if any.
+fn on_key(state: &mut State, key: KeyEvent) -> Option<Value> {
+    if key.kind == KeyEventKind::Release || is_quit(key) {
+        return None;
+    }
+    let horizontal = state.config.own_paddle().horizontal;
+    match key.code {
+        KeyCode::Up | KeyCode::Left if (key.code == KeyCode::Left) == horizontal => {
+            state.move_by(-STEP)
+        }
+        KeyCode::Down | KeyCode::Right if (key.code == KeyCode::Right) == horizontal => {
+            state.move_by(STEP)
+        }
+        KeyCode::Char(' ') => {
+            state.message = String::from("waiting for the other players");
+            Some(json!({
+                "event_type": "ready",
+                "event_body": { "player_id": state.config.player_id },
+            }))
+        }
+        KeyCode::Char(c @ ('r' | 's')) => {
+            state.message = String::from("waiting for opponent...");
+            Some(json!({
+                "event_type": "rematch_request",
+                "event_body": { "swap_sides": c == 's' },
+            }))
+        }
+        KeyCode::Char('a') => Some(json!({ "event_type": "rematch_accept", "event_body": {} })),
+        _ => None,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let Some(link) = env::args().nth(1) else {
+        eprintln!("usage: pong-tui <game link or id>");
+        std::process::exit(2);
+    };
+    let (server, path) = parse_link(&link);
+
+    let result = match join(&server, &path).await {
+        Ok(config) => play(&server, config).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pong_core::{game::GameMode, settings::MatchSettings};
+
+    use super::*;
+
+    fn state() -> State {
+        let config = GameConfig::new(
+            "123".into(),
+            "p1".into(),
+            GameMode::Singles,
+            &MatchSettings::default(),
+        );
+        State::new(config)
+    }
+
+    #[test]
+    fn links_are_turned_into_join_paths() {
+        assert_eq!(
+            parse_link("http://pong.example:3030/games/abc?player=p2"),
+            (
+                String::from("pong.example:3030"),
+                String::from("/games/abc/join?player=p2")
+            )
+        );
+        assert_eq!(
+            parse_link("abc"),
+            (
+                String::from(DEFAULT_SERVER),
+                String::from("/games/abc/join")
+            )
+        );
+    }
+
+    #[test]
+    fn server_messages_update_the_screen() {
+        let mut state = state();
+        let update = r#"{"event_type": "update_screen", "event_body": {
+            "balls": [{"id": 3, "x": 120, "y": 80}], "obstacles": [],
+            "players": {"p1": {"x": 0, "y": 5}, "p2": {"x": 789.6, "y": 40}},
+            "clock": 61000}}"#;
+        state.handle(serde_json::from_str(update).unwrap());
+        assert_eq!(state.balls, vec![(120.0, 80.0)]);
+        assert_eq!(state.paddles["p2"], (789.6, 40.0));
+        // our own paddle only moves when we press a key
+        assert_ne!(state.paddles["p1"], (0.0, 5.0));
+
+        let end_point = r#"{"event_type": "end_point", "event_body": {"p1_score": 1,
+            "p2_score": 0, "clock": 60000, "lives": {"p1": null, "p2": null}}}"#;
+        state.handle(serde_json::from_str(end_point).unwrap());
+        assert_eq!(state.score, (1, 0));
+        assert!(state.lives.is_empty());
+        assert!(state.status().starts_with("1:0  1:00"));
+    }
+
+    #[test]
+    fn canvas_scales_pixels_to_cells() {
+        let mut canvas = Canvas::new(4, 2, 100.0, 50.0);
+        canvas.put(60.0, 10.0, 'O');
+        canvas.fill(0.0, 0.0, 10.0, 50.0, '|');
+        // off the arena is clamped to its edge
+        canvas.put(500.0, 500.0, 'x');
+        let rows: Vec<String> = canvas.cells.iter().map(|r| r.iter().collect()).collect();
+        assert_eq!(rows, vec!["| O ", "|  x"]);
+    }
+
+    #[test]
+    fn rendering_draws_paddles_and_balls() {
+        let state = state();
+        let canvas = render(&state, 40, 20);
+        let rows: Vec<String> = canvas.cells.iter().map(|r| r.iter().collect()).collect();
+
+        assert!(rows.iter().any(|r| r.starts_with('█')));
+        assert!(rows.iter().any(|r| r.ends_with('▓')));
+        assert_eq!(
+            rows.iter().map(|r| r.matches('O').count()).sum::<usize>(),
+            1
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Bots connect a websocket to `/bot/ws?token=<token>`, optionally with
+//! `&game=<game id>` to join a particular game and `&player=<slot>` to take a
+//! particular slot in it. Without a game they are matched into a game open to
+//! bots, or a new singles game is opened for the next bot or human to join at
+//! `/games/<game id>`. Tokens are listed, comma separated, in the
+//! `PONG_BOT_TOKENS` environment variable, and no bot can connect if it isn't
+//! set. What they are sent once connected is described in `pong_core::bot`.
+
+use std::env;
+
+use lazy_static::lazy_static;
+
+pub use pong_core::bot::*;
+
+lazy_static! {
+    static ref BOT_TOKENS: Vec<String> = env::var("PONG_BOT_TOKENS")
+        .map(|tokens| {
+            tokens
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+}
+
+pub fn is_authorised(token: Option<&str>) -> bool {
+    token.is_some_and(|token| BOT_TOKENS.iter().any(|t| t == token))
+}
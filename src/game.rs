@@ -1,210 +1,127 @@
-use std::{collections::HashMap, time::Duration};
+use std::time::{Duration, Instant};
 
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use tokio::{sync::mpsc::UnboundedSender, time::sleep};
+use pong_core::protocol::{Notice, ServerEvent};
+use tokio::time::sleep;
+use tracing::{info, instrument};
 use warp::filters::ws::Message;
 
-use crate::{
-    player::Player,
-    pong::{Pong, P1_START, P2_START},
-    GAME_STORE,
-};
+pub use pong_core::game::*;
 
-pub const PLAYER_1: &str = "p1";
-pub const PLAYER_2: &str = "p2";
-pub const SCALING_FACTOR: f32 = 8.0;
+use crate::{league, metrics, tournament, CONNECTION_STORE, GAME_STORE};
 
-#[derive(Serialize, Deserialize)]
-struct StepResult {
-    ball_x: f32,
-    ball_y: f32,
-    left_wall_contact: bool,
-    right_wall_contact: bool,
+/// Starts `game` playing on its own task, unless it already is.
+pub fn start(game: &mut Game) {
+    if game.start() {
+        tokio::spawn(execute(game.id.clone()));
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct Game {
-    pub id: String,
-    players: HashMap<String, Player>,
-}
+#[instrument(skip_all, fields(game_id = %id))]
+async fn execute(id: String) {
+    let mut engine = match GAME_STORE.read().await.get(&id) {
+        Some(game) => Engine::new(game),
+        None => return,
+    };
 
-impl Game {
-    pub fn new(id: String) -> Self {
-        Game {
-            id,
-            players: HashMap::new(),
+    loop {
+        {
+            let mut games_write = metrics::game_store_lock(GAME_STORE.write()).await;
+            let Some(game) = games_write.get_mut(&id) else {
+                return;
+            };
+
+            let started = Instant::now();
+            let finished = engine.tick(game);
+            deliver(game).await;
+            metrics::TICK_DURATION.observe(started.elapsed());
+            if finished {
+                info!(winners = ?game.winners(), "game over");
+                report_result(game);
+                return;
+            }
         }
-    }
 
-    pub fn num_players(&self) -> usize {
-        self.players.len()
+        sleep(Duration::from_millis(15)).await;
     }
+}
 
-    pub fn add_player(&mut self) -> &str {
-        if self.players.get(PLAYER_1).is_none() {
-            self.players
-                .insert(String::from(PLAYER_1), Player::new(P1_START.0, P1_START.1));
-            PLAYER_1
-        } else if self.players.get(PLAYER_2).is_none() {
-            self.players
-                .insert(String::from(PLAYER_2), Player::new(P2_START.0, P2_START.1));
-            PLAYER_2
-        } else {
-            panic!("Too many players")
-        }
-    }
+pub fn message(event: &ServerEvent) -> Message {
+    Message::text(event.to_json())
+}
 
-    pub fn set_player_tx(&mut self, player_id: &str, tx: UnboundedSender<Message>) {
-        if let Some(mut player) = self.players.get(player_id).cloned() {
-            player.tx = Some(tx);
-            self.players.insert(player_id.into(), player);
-        }
-    }
+/// A message for people rather than clients to act on, like `kicked`.
+pub fn notice(kind: fn(Notice) -> ServerEvent, text: &str) -> Message {
+    message(&kind(Notice { text: text.into() }))
+}
 
-    pub fn set_player_ready(&mut self, player_id: &str) {
-        if let Some(mut player) = self.players.get(player_id).cloned() {
-            player.is_ready = true;
-            self.players.insert(player_id.into(), player);
+/// Takes a game out of the store, which stops it being played, and closes its
+/// players' websockets with a `game_terminated` notice giving `reason`. A
+/// competition game that hadn't finished is replayed in a new game. Returns
+/// false if there was no such game.
+pub async fn remove(game_id: &str, reason: &str) -> bool {
+    let removed = GAME_STORE.write().await.remove(game_id);
+    if let Some(players) = CONNECTION_STORE.write().await.remove(game_id) {
+        for tx in players.values() {
+            let _ = tx.send(notice(ServerEvent::GameTerminated, reason));
+            let _ = tx.send(Message::close());
         }
     }
-
-    pub fn update_player_pos(&mut self, player_id: &str, y: f32) {
-        if let Some(mut player) = self.players.get(player_id).cloned() {
-            player.y = y;
-            log::info!("{} pos: {},{}", player_id, player.x, y);
-            self.players.insert(player_id.into(), player);
+    match removed {
+        Some(game) => {
+            replay(&game);
+            true
         }
+        None => false,
     }
+}
 
-    pub fn inc_player_score(&mut self, player_id: &str) {
-        if let Some(mut player) = self.players.get(player_id).cloned() {
-            player.score += 1;
-            self.players.insert(player_id.into(), player);
+/// Sends the messages `game` has queued up to its players' websockets.
+pub async fn deliver(game: &mut Game) {
+    let outbox = game.take_outbox();
+    let connections = CONNECTION_STORE.read().await;
+    let Some(players) = connections.get(&game.id) else {
+        return;
+    };
+    for outgoing in outbox {
+        let message = message(&outgoing.message);
+        for player_id in &outgoing.to {
+            if let Some(tx) = players.get(player_id) {
+                let _ = tx.send(message.clone());
+            }
         }
     }
+}
 
-    pub fn both_players_ready(&self) -> bool {
-        self.players.get(PLAYER_1).is_some_and(|p| p.is_ready)
-            && self.players.get(PLAYER_2).is_some_and(|p| p.is_ready)
-    }
-
-    pub fn get_player(&self, player_id: &str) -> &Player {
-        if let Some(player) = self.players.get(player_id) {
-            return player;
+/// Has a removed game's competition set its match up again if it was still
+/// waiting on it. Like `report_result` this runs once the game lock is released.
+fn replay(game: &Game) {
+    match game.competition.clone() {
+        Some(Competition::Tournament(tournament_id)) => {
+            tokio::spawn(tournament::replay(tournament_id, game.id.clone()));
         }
-        panic!("no such player")
-    }
-
-    fn step(&self, pong: &mut Pong) -> StepResult {
-        let (x, y, left_wall_contact, right_wall_contact) =
-            pong.next(Some((self.get_player("p1").y, self.get_player("p2").y)));
-
-        StepResult {
-            ball_x: x,
-            ball_y: y,
-            left_wall_contact,
-            right_wall_contact,
+        Some(Competition::League(league_id)) => {
+            tokio::spawn(league::replay(league_id, game.id.clone()));
         }
-    }
-
-    pub fn start(&self) {
-        tokio::spawn(execute(self.id.clone()));
+        None => {}
     }
 }
 
-async fn execute(id: String) {
-    let mut p = Pong::new(6.0);
-
-    loop {
-        let mut games_write = GAME_STORE.write().await;
-        let game = games_write.get_mut(&id).unwrap();
-
-        let step_result = game.step(&mut p);
-
-        send_update_screen(&game, &step_result);
-        sleep(Duration::from_millis(15)).await;
-        if step_result.left_wall_contact {
-            game.inc_player_score(PLAYER_2);
-            game.update_player_pos(PLAYER_1, P1_START.1);
-            game.update_player_pos(PLAYER_2, P2_START.1);
-            send_end_point(&game);
-            p = Pong::new(6.0);
-        } else if step_result.right_wall_contact {
-            game.inc_player_score(PLAYER_1);
-            game.update_player_pos(PLAYER_1, P1_START.1);
-            game.update_player_pos(PLAYER_2, P2_START.1);
-            send_end_point(&game);
-            p = Pong::new(6.0);
+/// Hands a finished game's result to its competition. That takes the
+/// competition's lock, so it is left to run once the game lock is released.
+fn report_result(game: &Game) {
+    match game.competition.clone() {
+        Some(Competition::Tournament(tournament_id)) => {
+            let winners = game.winners().into_iter().map(String::from).collect();
+            tokio::spawn(tournament::record_result(
+                tournament_id,
+                game.id.clone(),
+                winners,
+            ));
         }
-    }
-}
-
-fn send_end_point(game: &Game) {
-    let p1_score = game.players.get(PLAYER_1).unwrap().score;
-    let p2_score = game.players.get(PLAYER_2).unwrap().score;
-
-    game.players.iter().for_each(|(_, player)| {
-        player
-            .clone()
-            .tx
-            .unwrap()
-            .send(Message::text(
-                json!({
-                    "event_type": "end_point",
-                    "event_body": {
-                        "p1_score": p1_score,
-                        "p2_score": p2_score,
-                    }
-
-                })
-                .to_string(),
-            ))
-            .unwrap();
-    });
-}
-
-fn send_update_screen(game: &Game, step_result: &StepResult) {
-    game.players.iter().for_each(|(id, player)| {
-        let opponent_id = match id.as_str() {
-            PLAYER_1 => PLAYER_2,
-            PLAYER_2 => PLAYER_1,
-            _ => panic!("bad player id"),
-        };
-        let opponent = game.players.get(opponent_id).unwrap();
-
-        player
-            .clone()
-            .tx
-            .unwrap()
-            .send(Message::text(
-                json!({
-                    "event_type": "update_screen",
-                    "event_body": {
-                        "ball_x": step_result.ball_x * SCALING_FACTOR,
-                        "ball_y": step_result.ball_y * SCALING_FACTOR,
-                        "opponent_y": opponent.y * SCALING_FACTOR
-                    }
-
-                })
-                .to_string(),
-            ))
-            .unwrap();
-    });
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn both_players_ready_true_when_both_ready() {
-        let mut game = Game::new("123".into());
-        game.add_player();
-        game.add_player();
-        game.set_player_ready("p1");
-        game.set_player_ready("p2");
-
-        assert_eq!(game.both_players_ready(), true)
+        Some(Competition::League(league_id)) => {
+            let scores = [PLAYER_1, PLAYER_2].map(|id| game.get_player(id).score);
+            tokio::spawn(league::record_result(league_id, game.id.clone(), scores));
+        }
+        None => {}
     }
 }
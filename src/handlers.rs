@@ -1,46 +1,270 @@
 use askama::Template;
-use futures_util::{SinkExt, StreamExt, TryFutureExt};
-use std::str::FromStr;
+use futures_util::{SinkExt, StreamExt};
+use pong_core::{
+    client_event::{parse_client_event, ClientEventType},
+    protocol::{GameConfig, ServerEvent},
+    settings::MatchSettings,
+};
+use std::{collections::HashMap, str::FromStr, sync::atomic::Ordering, time::Instant};
 use tokio::sync::mpsc::unbounded_channel;
 use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{debug, info, instrument, warn, Instrument, Span};
 use uuid::Uuid;
-use warp::{filters::ws::Ws, http::Uri, reject::Rejection, reply::Reply};
+use warp::{
+    filters::ws::{WebSocket, Ws},
+    http::{StatusCode, Uri},
+    reject::Rejection,
+    reply::{Reply, Response},
+};
 
 use crate::{
-    client_event::{parse_client_event, ClientEventType},
-    game::{Game, SCALING_FACTOR},
-    templates, GAME_STORE,
+    admin, bot,
+    game::{self, Game, GameMode},
+    league::{self, League},
+    metrics, shutdown, templates,
+    tournament::{self, Format, Tournament},
+    CONNECTION_STORE, GAME_STORE, LEAGUE_STORE, TOURNAMENT_STORE,
 };
 
-pub async fn create_game_handler(name: String) -> Result<impl Reply, Rejection> {
+/// Tells the client what was wrong with their request in plain text.
+fn error_reply(status: StatusCode, message: impl Into<String>) -> Response {
+    warp::reply::with_status(message.into(), status).into_response()
+}
+
+/// Refuses new work while the server is on its way down.
+fn restarting() -> Response {
+    error_reply(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "the server is restarting, try again shortly",
+    )
+}
+
+#[instrument(skip_all, fields(game_id))]
+pub async fn create_game_handler(form: HashMap<String, String>) -> Result<Response, Rejection> {
+    if shutdown::is_shutting_down() {
+        warn!("not starting a game while shutting down");
+        return Ok(restarting());
+    }
     let id: String = Uuid::new_v4().to_string();
+    Span::current().record("game_id", id.as_str());
+    let mode = form
+        .get("mode")
+        .and_then(|m| GameMode::from_str(m).ok())
+        .unwrap_or(GameMode::Singles);
+    let settings = MatchSettings::from_form(&form);
+    let mut game = Game::new(id.clone(), mode, settings);
+    game.open_to_bots = form.contains_key("open_to_bots");
 
     // is this concurrent-friendly?
-    GAME_STORE
-        .write()
-        .await
-        .insert(id.clone(), Game::new(id.clone()));
+    GAME_STORE.write().await.insert(id.clone(), game);
 
+    Ok(
+        warp::redirect::see_other(Uri::from_str(&format!("/games/{}", id)).unwrap())
+            .into_response(),
+    )
+}
+
+/// Joins the game in the first free slot, or the one given as `player` in the
+/// query, as tournament players are told which one is theirs.
+#[instrument(skip_all, fields(game_id = %game_id, player_id))]
+async fn join(
+    game_id: String,
+    query: &HashMap<String, String>,
+) -> Result<(GameConfig, MatchSettings), Rejection> {
+    let mut games_write = GAME_STORE.write().await;
+    let Some(game) = games_write.get_mut(&game_id) else {
+        return Err(warp::reject::not_found());
+    };
+    let wanted = query.get("player").map(|p| p.as_str());
+    if game.is_full() || wanted.is_some_and(|p| !game.is_slot_free(p)) {
+        // need better reply
+        warn!("game already full");
+        return Err(warp::reject::not_found());
+    }
+
+    let player_id = String::from(game.add_player_as(wanted));
+    Span::current().record("player_id", player_id.as_str());
+    debug!("joined");
+    Ok((
+        GameConfig::new(game_id, player_id, game.mode, &game.settings),
+        game.settings.clone(),
+    ))
+}
+
+pub async fn game_handler(
+    game_id: String,
+    query: HashMap<String, String>,
+) -> Result<Response, Rejection> {
+    if shutdown::is_shutting_down() {
+        warn!("not joining a game while shutting down");
+        return Ok(restarting());
+    }
+    let (config, settings) = join(game_id, &query).await?;
+    Ok(warp::reply::html(
+        templates::GameTemplate::new(&config, &settings)
+            .render()
+            .unwrap(),
+    )
+    .into_response())
+}
+
+/// Joins the game like the game page does, for clients other than browsers.
+pub async fn join_handler(
+    game_id: String,
+    query: HashMap<String, String>,
+) -> Result<Response, Rejection> {
+    if shutdown::is_shutting_down() {
+        warn!("not joining a game while shutting down");
+        return Ok(restarting());
+    }
+    let (config, _) = join(game_id, &query).await?;
+    Ok(warp::reply::json(&config).into_response())
+}
+
+pub async fn results_handler(game_id: String) -> Result<impl Reply, Rejection> {
+    if let Some(game) = GAME_STORE.read().await.get(&game_id) {
+        Ok(warp::reply::html(
+            templates::ResultsTemplate::new(game).render().unwrap(),
+        ))
+    } else {
+        Err(warp::reject::not_found())
+    }
+}
+
+/// The admin token from a query or form, if it is the right one.
+fn admin_token(params: &HashMap<String, String>) -> Option<&str> {
+    let token = params.get("token").map(|t| t.as_str());
+    if admin::is_authorised(token) {
+        token
+    } else {
+        warn!("admin token not recognised");
+        None
+    }
+}
+
+fn unauthorised() -> Response {
+    error_reply(StatusCode::UNAUTHORIZED, "admin token not recognised")
+}
+
+fn back_to_admin(token: &str) -> Response {
+    warp::redirect::see_other(Uri::from_str(&format!("/admin?token={}", token)).unwrap())
+        .into_response()
+}
+
+pub async fn admin_handler(query: HashMap<String, String>) -> Result<Response, Rejection> {
+    let Some(token) = admin_token(&query) else {
+        return Ok(unauthorised());
+    };
+    let notice = query
+        .get("kick")
+        .and_then(|k| admin::Kick::from_str(k).ok())
+        .map(|k| k.message());
+    Ok(warp::reply::html(
+        templates::AdminTemplate::new(token, admin::games().await, notice)
+            .render()
+            .unwrap(),
+    )
+    .into_response())
+}
+
+pub async fn admin_games_handler(query: HashMap<String, String>) -> Result<Response, Rejection> {
+    if admin_token(&query).is_none() {
+        return Ok(unauthorised());
+    }
+    Ok(warp::reply::json(&admin::games().await).into_response())
+}
+
+pub async fn admin_terminate_handler(
+    game_id: String,
+    form: HashMap<String, String>,
+) -> Result<Response, Rejection> {
+    let Some(token) = admin_token(&form) else {
+        return Ok(unauthorised());
+    };
+    admin::terminate(&game_id).await;
+    Ok(back_to_admin(token))
+}
+
+pub async fn admin_kick_handler(
+    game_id: String,
+    form: HashMap<String, String>,
+) -> Result<Response, Rejection> {
+    let Some(token) = admin_token(&form) else {
+        return Ok(unauthorised());
+    };
+    let kick = match form.get("player") {
+        Some(player_id) => admin::kick(&game_id, player_id).await,
+        None => admin::Kick::NoSuchPlayer,
+    };
     Ok(warp::redirect::see_other(
-        Uri::from_str(&format!("/games/{}", id)).unwrap(),
+        Uri::from_str(&format!("/admin?token={}&kick={}", token, kick.name())).unwrap(),
+    )
+    .into_response())
+}
+
+pub async fn admin_announce_handler(form: HashMap<String, String>) -> Result<Response, Rejection> {
+    let Some(token) = admin_token(&form) else {
+        return Ok(unauthorised());
+    };
+    if let Some(text) = form.get("text").filter(|t| !t.is_empty()) {
+        admin::announce(text).await;
+    }
+    Ok(back_to_admin(token))
+}
+
+pub async fn metrics_handler() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::with_header(
+        metrics::render().await,
+        "content-type",
+        "text/plain; version=0.0.4",
     ))
 }
 
-pub async fn game_handler(game_id: String) -> Result<impl Reply, Rejection> {
-    let mut games_write = GAME_STORE.write().await;
-    if let Some(mut game) = games_write.get(&game_id).cloned() {
-        if game.num_players() >= 2 {
-            // need better reply
-            eprintln!("game already full");
-            return Err(warp::reject::not_found());
+pub async fn create_tournament_handler(
+    form: HashMap<String, String>,
+) -> Result<Response, Rejection> {
+    if shutdown::is_shutting_down() {
+        warn!("not starting a tournament while shutting down");
+        return Ok(restarting());
+    }
+    let id: String = Uuid::new_v4().to_string();
+    let format = form
+        .get("format")
+        .and_then(|f| Format::from_str(f).ok())
+        .unwrap_or(Format::SingleElimination);
+    let entrants = entrants_from_form(&form);
+    let name = name_from_form(&form, "Tournament");
+
+    let mut tournament = match Tournament::new(
+        id.clone(),
+        name,
+        format,
+        entrants,
+        MatchSettings::from_form(&form),
+    ) {
+        Ok(tournament) => tournament,
+        Err(err) => {
+            warn!("could not create tournament: {}", err);
+            return Ok(error_reply(StatusCode::BAD_REQUEST, err));
         }
+    };
 
-        let player_id = String::from(game.add_player());
-        games_write.insert(game_id.clone(), game.clone());
-        eprintln!("games {:?}", games_write);
+    let mut tournaments_write = TOURNAMENT_STORE.write().await;
+    tournament::schedule(&mut tournament, &mut *GAME_STORE.write().await);
+    tournaments_write.insert(id.clone(), tournament);
 
+    Ok(
+        warp::redirect::see_other(Uri::from_str(&format!("/tournaments/{}", id)).unwrap())
+            .into_response(),
+    )
+}
+
+pub async fn tournament_handler(tournament_id: String) -> Result<impl Reply, Rejection> {
+    let tournaments_read = TOURNAMENT_STORE.read().await;
+    if let Some(tournament) = tournaments_read.get(&tournament_id) {
+        let games_read = GAME_STORE.read().await;
         Ok(warp::reply::html(
-            templates::GameTemplate::new(game_id, player_id)
+            templates::TournamentTemplate::new(tournament, &games_read)
                 .render()
                 .unwrap(),
         ))
@@ -49,69 +273,295 @@ pub async fn game_handler(game_id: String) -> Result<impl Reply, Rejection> {
     }
 }
 
+pub async fn create_league_handler(form: HashMap<String, String>) -> Result<Response, Rejection> {
+    if shutdown::is_shutting_down() {
+        warn!("not starting a league while shutting down");
+        return Ok(restarting());
+    }
+    let id: String = Uuid::new_v4().to_string();
+
+    let mut league = match League::new(
+        id.clone(),
+        name_from_form(&form, "League"),
+        entrants_from_form(&form),
+        MatchSettings::from_form(&form),
+    ) {
+        Ok(league) => league,
+        Err(err) => {
+            warn!("could not create league: {}", err);
+            return Ok(error_reply(StatusCode::BAD_REQUEST, err));
+        }
+    };
+
+    let mut leagues_write = LEAGUE_STORE.write().await;
+    league::schedule(&mut league, &mut *GAME_STORE.write().await);
+    leagues_write.insert(id.clone(), league);
+
+    Ok(
+        warp::redirect::see_other(Uri::from_str(&format!("/leagues/{}", id)).unwrap())
+            .into_response(),
+    )
+}
+
+pub async fn league_handler(league_id: String) -> Result<impl Reply, Rejection> {
+    let leagues_read = LEAGUE_STORE.read().await;
+    if let Some(league) = leagues_read.get(&league_id) {
+        let games_read = GAME_STORE.read().await;
+        Ok(warp::reply::html(
+            templates::LeagueTemplate::new(league, &games_read)
+                .render()
+                .unwrap(),
+        ))
+    } else {
+        Err(warp::reject::not_found())
+    }
+}
+
+pub async fn league_standings_handler(league_id: String) -> Result<impl Reply, Rejection> {
+    if let Some(league) = LEAGUE_STORE.read().await.get(&league_id) {
+        Ok(warp::reply::json(&league.standings()))
+    } else {
+        Err(warp::reject::not_found())
+    }
+}
+
+/// Entrant names from the form's `entrants` field, one per line, in order and
+/// without duplicates.
+fn entrants_from_form(form: &HashMap<String, String>) -> Vec<String> {
+    let mut entrants: Vec<String> = vec![];
+    for name in form
+        .get("entrants")
+        .map_or("", |e| e.as_str())
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !entrants.iter().any(|e| e == name) {
+            entrants.push(String::from(name));
+        }
+    }
+    entrants
+}
+
+fn name_from_form(form: &HashMap<String, String>, default: &str) -> String {
+    form.get("name")
+        .filter(|n| !n.is_empty())
+        .cloned()
+        .unwrap_or_else(|| String::from(default))
+}
+
 pub async fn ws_handler(
     (game_id, player_id): (String, String),
     ws: Ws,
-) -> Result<impl Reply, Rejection> {
-    Ok(ws.on_upgrade(|socket| async move {
-        let (mut ws_tx, mut ws_rx) = socket.split();
-        let (tx, rx) = unbounded_channel();
-        let mut rx = UnboundedReceiverStream::new(rx);
-
-        {
-            let mut games_write = GAME_STORE.write().await;
-            if let Some(mut game) = games_write.get(&game_id).cloned() {
-                game.set_player_tx(&player_id, tx);
-                games_write.insert(game_id.clone(), game);
-            }
+) -> Result<Response, Rejection> {
+    let kicked = GAME_STORE
+        .read()
+        .await
+        .get(&game_id)
+        .is_some_and(|game| game.is_kicked(&player_id));
+    if kicked {
+        warn!(game_id, player_id, "kicked player tried to reconnect");
+        return Ok(error_reply(
+            StatusCode::FORBIDDEN,
+            "you were removed from this game",
+        ));
+    }
+    Ok(ws
+        .on_upgrade(|socket| play(socket, game_id, player_id, false))
+        .into_response())
+}
+
+/// Matches an authorised bot into a game, see the `bot` module for the protocol.
+#[instrument(skip_all, fields(game_id, player_id))]
+pub async fn bot_ws_handler(query: HashMap<String, String>, ws: Ws) -> Result<Response, Rejection> {
+    if !bot::is_authorised(query.get("token").map(|t| t.as_str())) {
+        warn!("bot token not recognised");
+        return Ok(error_reply(
+            StatusCode::UNAUTHORIZED,
+            "bot token not recognised",
+        ));
+    }
+    if shutdown::is_shutting_down() && !query.contains_key("game") {
+        warn!("not matching bots while shutting down");
+        return Ok(restarting());
+    }
+
+    let joined = bot::join(
+        &mut *GAME_STORE.write().await,
+        query.get("game").map(|g| g.as_str()),
+        query.get("player").map(|p| p.as_str()),
+    );
+    match joined {
+        Ok((game_id, player_id)) => {
+            Span::current().record("game_id", game_id.as_str());
+            Span::current().record("player_id", player_id.as_str());
+            Ok(ws
+                .on_upgrade(|socket| play(socket, game_id, player_id, true))
+                .into_response())
         }
+        Err(err) => {
+            // need better reply
+            warn!("{}", err);
+            Err(warp::reject::not_found())
+        }
+    }
+}
+
+#[instrument(skip(socket), fields(game_id = %game_id, player_id = %player_id))]
+async fn play(socket: WebSocket, game_id: String, player_id: String, bot: bool) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    let (tx, rx) = unbounded_channel();
+    metrics::WEBSOCKETS.fetch_add(1, Ordering::Relaxed);
+    let mut rx = UnboundedReceiverStream::new(rx);
+
+    if bot {
+        if let Some(game) = GAME_STORE.read().await.get(&game_id) {
+            let _ = tx.send(game::message(&bot::matched(game, &player_id)));
+        }
+    }
+    CONNECTION_STORE
+        .write()
+        .await
+        .entry(game_id.clone())
+        .or_default()
+        .insert(player_id.clone(), tx.clone());
 
-        // spawn broadcast task
-        tokio::spawn(async move {
+    // spawn broadcast task
+    tokio::spawn(
+        async move {
             while let Some(message) = rx.next().await {
-                ws_tx
-                    .send(message)
-                    .unwrap_or_else(|e| {
-                        eprintln!("websocket send error: {}", e);
-                    })
-                    .await;
-            }
-        });
-
-        while let Some(result) = ws_rx.next().await {
-            if let Ok(msg) = result {
-                match parse_client_event(msg.to_str().unwrap()) {
-                    Some(ClientEventType::ReadyEvent) => {
-                        let mut games_write = GAME_STORE.write().await;
-                        if let Some(mut game) = games_write.get(&game_id).cloned() {
-                            game.set_player_ready(&player_id);
-                            games_write.insert(game_id.clone(), game.clone());
-                            if game.both_players_ready() {
-                                eprintln!("starting game");
-                                game.start()
-                            }
-                        }
-                    }
-                    Some(ClientEventType::MoveEvent(event)) => {
-                        let mut games_write = GAME_STORE.write().await;
-                        if let Some(mut game) = games_write.get(&game_id).cloned() {
-                            game.update_player_pos(&player_id, event.y / SCALING_FACTOR);
-                            games_write.insert(game_id.clone(), game);
-                        }
-                    }
-                    None => {
-                        log::info!("Event type not recognised");
+                match ws_tx.send(message).await {
+                    Ok(()) => {
+                        metrics::MESSAGES_SENT.fetch_add(1, Ordering::Relaxed);
                     }
-                };
-            } else {
-                log::info!("HELLO");
+                    Err(e) => warn!("websocket send error: {}", e),
+                }
             }
+        }
+        .in_current_span(),
+    );
+
+    // bots don't have a button to press
+    if bot {
+        handle_client_event(&game_id, &player_id, ClientEventType::ReadyEvent).await;
+    }
+
+    while let Some(result) = ws_rx.next().await {
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!("websocket receive error: {}", e);
+                continue;
+            }
+        };
+        // pings and closes are handled by warp
+        let Ok(text) = msg.to_str() else {
+            continue;
+        };
+        metrics::MESSAGES_RECEIVED.fetch_add(1, Ordering::Relaxed);
+        match parse_client_event(text) {
+            Some(event) => handle_client_event(&game_id, &player_id, event).await,
+            None => {
+                metrics::PARSE_ERRORS.fetch_add(1, Ordering::Relaxed);
+                info!(text, "event type not recognised");
+            }
+        };
+
+        // Send the message to the broadcast task
+        // tx.send(msg).unwrap();
+    }
+
+    // the game counts as left from now, not from the last message
+    if let Some(game) = GAME_STORE.write().await.get_mut(&game_id) {
+        game.last_active = Instant::now();
+    }
+    // unless someone has connected in our place, or we were kicked
+    let mut connections = CONNECTION_STORE.write().await;
+    if let Some(players) = connections.get_mut(&game_id) {
+        if players.get(&player_id).is_some_and(|t| t.same_channel(&tx)) {
+            players.remove(&player_id);
+        }
+        if players.is_empty() {
+            connections.remove(&game_id);
+        }
+    }
+    metrics::WEBSOCKETS.fetch_sub(1, Ordering::Relaxed);
+    info!("disconnected")
+}
 
-            // Send the message to the broadcast task
-            // tx.send(msg).unwrap();
+async fn handle_client_event(game_id: &str, player_id: &str, event: ClientEventType) {
+    match event {
+        ClientEventType::ReadyEvent => {
+            if shutdown::is_shutting_down() {
+                warn!("not starting a game while shutting down");
+                tell_players_restarting(game_id).await;
+                return;
+            }
+            let mut games_write = metrics::game_store_lock(GAME_STORE.write()).await;
+            if let Some(mut game) = games_write.get(game_id).cloned() {
+                game.set_player_ready(player_id);
+                if game.both_players_ready() {
+                    info!("starting game");
+                    game::start(&mut game)
+                }
+                games_write.insert(game_id.to_string(), game);
+            }
         }
+        ClientEventType::MoveEvent(event) => {
+            let mut games_write = metrics::game_store_lock(GAME_STORE.write()).await;
+            if let Some(mut game) = games_write.get(game_id).cloned() {
+                let scale = game.settings.scaling_factor;
+                game.update_player_pos(
+                    player_id,
+                    event.x.map(|x| x / scale),
+                    event.y.map(|y| y / scale),
+                );
+                games_write.insert(game_id.to_string(), game);
+            }
+        }
+        ClientEventType::ActionEvent(event) => {
+            if let Some(game) = metrics::game_store_lock(GAME_STORE.write())
+                .await
+                .get_mut(game_id)
+            {
+                game.act(player_id, event.tick, event.position);
+                game::deliver(game).await;
+            }
+        }
+        ClientEventType::RematchRequestEvent(event) => {
+            let mut games_write = metrics::game_store_lock(GAME_STORE.write()).await;
+            if let Some(game) = games_write.get_mut(game_id) {
+                game.request_rematch(player_id, event.swap_sides);
+                game::deliver(game).await;
+            }
+        }
+        ClientEventType::RematchAcceptEvent => {
+            if shutdown::is_shutting_down() {
+                warn!("not starting a rematch while shutting down");
+                tell_players_restarting(game_id).await;
+                return;
+            }
+            let mut games_write = metrics::game_store_lock(GAME_STORE.write()).await;
+            if let Some(game) = games_write.get_mut(game_id) {
+                if game.accept_rematch(player_id) {
+                    info!("starting rematch");
+                    game::deliver(game).await;
+                    game::start(game)
+                }
+            }
+        }
+    }
+}
 
-        // clients.write().await.retain(|client| client.id != id);
-        eprintln!("disconnected")
-    }))
+/// Lets everyone in a game that won't be started know why.
+async fn tell_players_restarting(game_id: &str) {
+    if let Some(players) = CONNECTION_STORE.read().await.get(game_id) {
+        let notice = game::notice(
+            ServerEvent::Announcement,
+            "The server is restarting, start a new game once it is back",
+        );
+        for tx in players.values() {
+            let _ = tx.send(notice.clone());
+        }
+    }
 }
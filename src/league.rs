@@ -0,0 +1,299 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use pong_core::settings::MatchSettings;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    game::{Competition, Game, GameMode},
+    GAME_STORE, LEAGUE_STORE,
+};
+
+/// One game of the league. `players` index into `League::players`, the first
+/// plays as `PLAYER_1`.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub round: usize,
+    pub players: [usize; 2],
+    pub game_id: Option<String>,
+    /// Both players' points once the game has finished.
+    pub result: Option<[u32; 2]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct League {
+    pub id: String,
+    pub name: String,
+    pub players: Vec<String>,
+    /// Used for every game in the league.
+    pub settings: MatchSettings,
+    pub fixtures: Vec<Fixture>,
+}
+
+/// A row of the league table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Standing {
+    pub player: String,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub points_for: u32,
+    pub points_against: u32,
+    pub point_difference: i64,
+}
+
+impl League {
+    /// Draws up fixtures so that every player meets every other player once.
+    pub fn new(
+        id: String,
+        name: String,
+        players: Vec<String>,
+        settings: MatchSettings,
+    ) -> Result<Self, String> {
+        if players.len() < 2 {
+            return Err(String::from("a league needs at least two players"));
+        }
+
+        let fixtures = round_robin(players.len())
+            .into_iter()
+            .map(|(round, players)| Fixture {
+                round,
+                players,
+                game_id: None,
+                result: None,
+            })
+            .collect();
+
+        Ok(League {
+            id,
+            name,
+            players,
+            settings,
+            fixtures,
+        })
+    }
+
+    /// Records the points scored in `game_id`. Returns false if no fixture is
+    /// waiting on that game.
+    pub fn record_result(&mut self, game_id: &str, scores: [u32; 2]) -> bool {
+        match self
+            .fixtures
+            .iter_mut()
+            .find(|f| f.result.is_none() && f.game_id.as_deref() == Some(game_id))
+        {
+            Some(fixture) => {
+                fixture.result = Some(scores);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets the game for the unplayed fixture in `game_id`, so that
+    /// `schedule` gives it a new one. Returns false if no fixture is waiting
+    /// on that game.
+    pub fn unschedule(&mut self, game_id: &str) -> bool {
+        match self
+            .fixtures
+            .iter_mut()
+            .find(|f| f.result.is_none() && f.game_id.as_deref() == Some(game_id))
+        {
+            Some(fixture) => {
+                fixture.game_id = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The league table, ordered by wins, then draws, then point difference,
+    /// then points scored.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .players
+            .iter()
+            .map(|player| Standing {
+                player: player.clone(),
+                played: 0,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                points_for: 0,
+                points_against: 0,
+                point_difference: 0,
+            })
+            .collect();
+
+        for fixture in &self.fixtures {
+            let Some(scores) = fixture.result else {
+                continue;
+            };
+            for (seat, player) in fixture.players.iter().enumerate() {
+                let (scored, conceded) = (scores[seat], scores[1 - seat]);
+                let standing = &mut standings[*player];
+                standing.played += 1;
+                match scored.cmp(&conceded) {
+                    Ordering::Greater => standing.wins += 1,
+                    Ordering::Equal => standing.draws += 1,
+                    Ordering::Less => standing.losses += 1,
+                }
+                standing.points_for += scored;
+                standing.points_against += conceded;
+                standing.point_difference += i64::from(scored) - i64::from(conceded);
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.wins
+                .cmp(&a.wins)
+                .then(b.draws.cmp(&a.draws))
+                .then(b.point_difference.cmp(&a.point_difference))
+                .then(b.points_for.cmp(&a.points_for))
+                .then(a.player.cmp(&b.player))
+        });
+        standings
+    }
+}
+
+/// Pairs up `n` players with the circle method, each meeting every other once
+/// and nobody playing twice in a round. With an odd number one player sits
+/// out each round.
+fn round_robin(n: usize) -> Vec<(usize, [usize; 2])> {
+    // `n` stands for the bye when the number of players is odd
+    let mut circle: Vec<usize> = (0..n + n % 2).collect();
+    let mut fixtures = vec![];
+
+    for round in 1..circle.len() {
+        let half = circle.len() / 2;
+        for i in 0..half {
+            let (home, away) = (circle[i], circle[circle.len() - 1 - i]);
+            if home < n && away < n {
+                fixtures.push((round, [home, away]));
+            }
+        }
+        // keep the first player in place and rotate everyone else
+        circle[1..].rotate_right(1);
+    }
+    fixtures
+}
+
+/// Creates a game for every fixture that doesn't have one yet.
+pub fn schedule(league: &mut League, games: &mut HashMap<String, Game>) {
+    for fixture in league.fixtures.iter_mut().filter(|f| f.game_id.is_none()) {
+        let game_id = Uuid::new_v4().to_string();
+        let mut game = Game::new(game_id.clone(), GameMode::Singles, league.settings.clone());
+        game.competition = Some(Competition::League(league.id.clone()));
+        games.insert(game_id.clone(), game);
+        fixture.game_id = Some(game_id);
+    }
+}
+
+pub async fn record_result(league_id: String, game_id: String, scores: [u32; 2]) {
+    if let Some(league) = LEAGUE_STORE.write().await.get_mut(&league_id) {
+        league.record_result(&game_id, scores);
+    }
+}
+
+/// Gives the fixture played in `game_id`, which was taken away before it
+/// finished, a new game.
+pub async fn replay(league_id: String, game_id: String) {
+    let mut leagues_write = LEAGUE_STORE.write().await;
+    if let Some(league) = leagues_write.get_mut(&league_id) {
+        if league.unschedule(&game_id) {
+            schedule(league, &mut *GAME_STORE.write().await);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everyone_plays_everyone_once() {
+        let fixtures = round_robin(5);
+
+        assert_eq!(fixtures.len(), 10);
+        for a in 0..5 {
+            for b in (a + 1)..5 {
+                let meetings = fixtures
+                    .iter()
+                    .filter(|(_, p)| p.contains(&a) && p.contains(&b))
+                    .count();
+                assert_eq!(meetings, 1);
+            }
+        }
+        for round in 1..=5 {
+            let mut playing: Vec<usize> = fixtures
+                .iter()
+                .filter(|(r, _)| *r == round)
+                .flat_map(|(_, p)| *p)
+                .collect();
+            playing.sort();
+            playing.dedup();
+            assert_eq!(playing.len(), 4);
+        }
+    }
+
+    #[test]
+    fn standings_rank_by_wins_then_point_difference() {
+        let players = vec!["ann".into(), "bob".into(), "cat".into()];
+        let mut league = League::new(
+            "l".into(),
+            "league".into(),
+            players,
+            MatchSettings::default(),
+        )
+        .unwrap();
+        for (i, fixture) in league.fixtures.iter_mut().enumerate() {
+            fixture.game_id = Some(i.to_string());
+        }
+
+        let game_between = |league: &League, a: usize, b: usize| {
+            let fixture = league
+                .fixtures
+                .iter()
+                .find(|f| f.players.contains(&a) && f.players.contains(&b))
+                .unwrap();
+            let scores = if fixture.players[0] == a {
+                [5, 1]
+            } else {
+                [1, 5]
+            };
+            (fixture.game_id.clone().unwrap(), scores)
+        };
+        // ann beats bob 5-1, bob beats cat 5-1, cat beats ann 5-1
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let (game_id, scores) = game_between(&league, a, b);
+            assert!(league.record_result(&game_id, scores));
+        }
+        assert!(!league.record_result("0", [5, 0]));
+
+        let standings = league.standings();
+        assert!(standings.iter().all(|s| s.played == 2 && s.wins == 1));
+        assert_eq!(standings[0].player, "ann");
+        assert_eq!(standings[0].points_for, 6);
+        assert_eq!(standings[0].point_difference, 0);
+    }
+
+    #[test]
+    fn draws_are_not_counted_as_losses() {
+        let players = vec!["ann".into(), "bob".into()];
+        let mut league = League::new(
+            "l".into(),
+            "league".into(),
+            players,
+            MatchSettings::default(),
+        )
+        .unwrap();
+        league.fixtures[0].game_id = Some("0".into());
+
+        assert!(league.record_result("0", [3, 3]));
+
+        let standings = league.standings();
+        assert!(standings
+            .iter()
+            .all(|s| s.played == 1 && s.wins == 0 && s.draws == 1 && s.losses == 0));
+    }
+}
@@ -1,25 +1,55 @@
-mod client_event;
+mod admin;
+mod bot;
 mod game;
 mod handlers;
-mod player;
-mod pong;
+mod league;
+mod metrics;
+mod reaper;
+mod shutdown;
+mod snapshot;
 mod templates;
+mod tournament;
 
 use game::Game;
 use lazy_static::lazy_static;
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
-use tokio::sync::RwLock;
-use warp::Filter;
+use league::League;
+use std::{collections::HashMap, env, net::SocketAddr, sync::Arc};
+use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use tournament::Tournament;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use warp::{filters::ws::Message, Filter};
 
 type GameStore = Arc<RwLock<HashMap<String, Game>>>;
+/// Each game's players' websockets, by game id and then player id.
+type ConnectionStore = Arc<RwLock<HashMap<String, HashMap<String, UnboundedSender<Message>>>>>;
+type TournamentStore = Arc<RwLock<HashMap<String, Tournament>>>;
+type LeagueStore = Arc<RwLock<HashMap<String, League>>>;
 
+// when holding a tournament or league lock as well as the game lock, take the
+// game lock last; take the connection lock after the game lock
 lazy_static! {
     pub static ref GAME_STORE: GameStore = Arc::new(RwLock::new(HashMap::new()));
+    pub static ref CONNECTION_STORE: ConnectionStore = Arc::new(RwLock::new(HashMap::new()));
+    pub static ref TOURNAMENT_STORE: TournamentStore = Arc::new(RwLock::new(HashMap::new()));
+    pub static ref LEAGUE_STORE: LeagueStore = Arc::new(RwLock::new(HashMap::new()));
 }
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    // RUST_LOG picks what is logged, e.g. `pong=debug,warp=info`, and
+    // PONG_LOG_FORMAT=json logs one JSON object a line
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let logs = tracing_subscriber::fmt().with_env_filter(filter);
+    if env::var("PONG_LOG_FORMAT").is_ok_and(|format| format == "json") {
+        logs.json().init();
+    } else {
+        logs.init();
+    }
+
+    let shutdown = shutdown::Config::from_env();
+    snapshot::restore(&shutdown.file).await;
+    tokio::spawn(reaper::run(reaper::Ttls::from_env()));
 
     let home_page = warp::path::end().and(warp::fs::file("templates/index.html"));
 
@@ -27,32 +57,113 @@ async fn main() {
 
     let game_page = warp::path("games")
         .and(warp::path::param())
-        .map(|game_id: String| game_id)
+        .and(warp::query::<HashMap<String, String>>())
         .and_then(handlers::game_handler);
 
+    let join_route = warp::path!("games" / String / "join")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(handlers::join_handler);
+
+    let results_page = warp::path!("games" / String / "results")
+        .and(warp::get())
+        .and_then(handlers::results_handler);
+
     let create_game_route = warp::path!("create_game")
         .and(warp::post())
         .and(warp::body::form())
-        .map(|body: HashMap<String, String>| body.get("name").unwrap().clone())
         .and_then(handlers::create_game_handler);
 
+    let tournament_page = warp::path!("tournaments" / String)
+        .and(warp::get())
+        .and_then(handlers::tournament_handler);
+
+    let create_tournament_route = warp::path!("create_tournament")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and_then(handlers::create_tournament_handler);
+
+    let league_page = warp::path!("leagues" / String)
+        .and(warp::get())
+        .and_then(handlers::league_handler);
+
+    let league_standings_route = warp::path!("leagues" / String / "standings")
+        .and(warp::get())
+        .and_then(handlers::league_standings_handler);
+
+    let create_league_route = warp::path!("create_league")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and_then(handlers::create_league_handler);
+
     let ws_route = warp::path!("ws" / String / String)
         .map(|game_id: String, player_id: String| (game_id, player_id))
         .and(warp::ws())
         .and_then(handlers::ws_handler);
 
+    let admin_page = warp::path!("admin")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(handlers::admin_handler);
+
+    let admin_games_route = warp::path!("admin" / "games")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(handlers::admin_games_handler);
+
+    let admin_terminate_route = warp::path!("admin" / "games" / String / "terminate")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and_then(handlers::admin_terminate_handler);
+
+    let admin_kick_route = warp::path!("admin" / "games" / String / "kick")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and_then(handlers::admin_kick_handler);
+
+    let admin_announce_route = warp::path!("admin" / "announce")
+        .and(warp::post())
+        .and(warp::body::form())
+        .and_then(handlers::admin_announce_handler);
+
+    let metrics_route = warp::path!("metrics")
+        .and(warp::get())
+        .and_then(handlers::metrics_handler);
+
+    let bot_ws_route = warp::path!("bot" / "ws")
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::ws())
+        .and_then(handlers::bot_ws_handler);
+
+    // before the game page, which would take any path under /games
     let routes = home_page
         .or(hello_page)
+        .or(results_page)
+        .or(join_route)
         .or(game_page)
         .or(create_game_route)
+        .or(tournament_page)
+        .or(create_tournament_route)
+        .or(league_page)
+        .or(league_standings_route)
+        .or(create_league_route)
         .or(ws_route)
-        .with(warp::log("pong"));
+        .or(bot_ws_route)
+        .or(metrics_route)
+        .or(admin_page)
+        .or(admin_games_route)
+        .or(admin_terminate_route)
+        .or(admin_kick_route)
+        .or(admin_announce_route)
+        .with(warp::trace::request());
 
     let addr = format!("{}:{}", "0.0.0.0", 3030)
         .parse::<SocketAddr>()
         .expect("Invalid address format");
 
-    eprintln!("Pong server running on {:?}", addr);
+    info!("Pong server running on {:?}", addr);
 
-    warp::serve(routes).run(addr).await;
+    let (_, server) =
+        warp::serve(routes).bind_with_graceful_shutdown(addr, shutdown::on_signal(shutdown));
+    server.await;
 }
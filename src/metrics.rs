@@ -0,0 +1,187 @@
+//! Counters and histograms served at `/metrics` in the Prometheus text
+//! format. Gauges that can be read off the stores, like the number of games,
+//! are worked out when scraped instead.
+
+use std::{
+    fmt::Write,
+    future::Future,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+use crate::{game::GameStatus, GAME_STORE};
+
+pub static WEBSOCKETS: AtomicI64 = AtomicI64::new(0);
+pub static MESSAGES_SENT: AtomicU64 = AtomicU64::new(0);
+pub static MESSAGES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+pub static PARSE_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    pub static ref TICK_DURATION: Histogram =
+        Histogram::new(&[0.0005, 0.001, 0.0025, 0.005, 0.01, 0.015, 0.025, 0.05, 0.1]);
+    pub static ref GAME_STORE_WAIT: Histogram =
+        Histogram::new(&[0.00001, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1]);
+}
+
+/// Counts of observations at or under each bound, in seconds.
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// One more than there are bounds, for observations over all of them.
+    counts: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut total = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            total += count.load(Ordering::Relaxed);
+            let le = self
+                .bounds
+                .get(i)
+                .map_or(String::from("+Inf"), |bound| bound.to_string());
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, total);
+        }
+        let sum = self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, total);
+    }
+}
+
+/// Waits for a `GAME_STORE` lock, recording how long that took.
+pub async fn game_store_lock<T>(lock: impl Future<Output = T>) -> T {
+    let started = Instant::now();
+    let guard = lock.await;
+    GAME_STORE_WAIT.observe(started.elapsed());
+    guard
+}
+
+fn render_value(out: &mut String, name: &str, kind: &str, help: &str, value: impl ToString) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value.to_string());
+}
+
+pub async fn render() -> String {
+    let mut out = String::new();
+
+    let (games, players_waiting) = {
+        let games = GAME_STORE.read().await;
+        let count = |status| games.values().filter(|g| g.status == status).count();
+        let players_waiting: usize = games
+            .values()
+            .filter(|g| g.status == GameStatus::Waiting)
+            .map(|g| g.num_players())
+            .sum();
+        (
+            [
+                ("waiting", count(GameStatus::Waiting)),
+                ("in_progress", count(GameStatus::InProgress)),
+                ("finished", count(GameStatus::Finished)),
+            ],
+            players_waiting,
+        )
+    };
+    let _ = writeln!(out, "# HELP pong_games Games in the store by status.");
+    let _ = writeln!(out, "# TYPE pong_games gauge");
+    for (status, count) in games {
+        let _ = writeln!(out, "pong_games{{status=\"{}\"}} {}", status, count);
+    }
+    render_value(
+        &mut out,
+        "pong_players_waiting",
+        "gauge",
+        "Players in games that haven't started yet.",
+        players_waiting,
+    );
+    render_value(
+        &mut out,
+        "pong_websockets",
+        "gauge",
+        "Connected player and bot websockets.",
+        WEBSOCKETS.load(Ordering::Relaxed),
+    );
+    render_value(
+        &mut out,
+        "pong_messages_sent_total",
+        "counter",
+        "Messages sent to websockets.",
+        MESSAGES_SENT.load(Ordering::Relaxed),
+    );
+    render_value(
+        &mut out,
+        "pong_messages_received_total",
+        "counter",
+        "Text messages received from websockets.",
+        MESSAGES_RECEIVED.load(Ordering::Relaxed),
+    );
+    render_value(
+        &mut out,
+        "pong_parse_errors_total",
+        "counter",
+        "Received messages that weren't a known client event.",
+        PARSE_ERRORS.load(Ordering::Relaxed),
+    );
+    TICK_DURATION.render(
+        &mut out,
+        "pong_tick_duration_seconds",
+        "Time taken by one tick of a game, sending its messages included.",
+    );
+    GAME_STORE_WAIT.render(
+        &mut out,
+        "pong_game_store_lock_wait_seconds",
+        "Time game ticks and client events spent waiting for the game store lock.",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new(&[0.001, 0.01]);
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_secs(1));
+
+        let mut out = String::new();
+        histogram.render(&mut out, "tick", "Ticks.");
+        assert_eq!(
+            out,
+            "# HELP tick Ticks.\n\
+             # TYPE tick histogram\n\
+             tick_bucket{le=\"0.001\"} 1\n\
+             tick_bucket{le=\"0.01\"} 3\n\
+             tick_bucket{le=\"+Inf\"} 4\n\
+             tick_sum 1.0105\n\
+             tick_count 4\n"
+        );
+    }
+}
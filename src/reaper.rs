@@ -0,0 +1,113 @@
+//! Clears out games nobody is playing. How long games are kept is set in
+//! seconds with `PONG_WAITING_TTL`, for games nobody has started,
+//! `PONG_IDLE_TTL`, for games in progress with nobody connected, and
+//! `PONG_FINISHED_TTL`, for finished games. Games waiting to be played in a
+//! tournament or league are kept until they are played, and ones left part way
+//! through are set up again as new games.
+
+use std::{env, time::Duration};
+
+use tokio::time::interval;
+use tracing::{info, instrument};
+
+use crate::{
+    game::{self, Game, GameStatus},
+    CONNECTION_STORE, GAME_STORE,
+};
+
+const SWEEP_EVERY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ttls {
+    pub waiting: Duration,
+    pub idle: Duration,
+    pub finished: Duration,
+}
+
+impl Ttls {
+    pub fn from_env() -> Self {
+        let ttl = |name: &str, default: u64| {
+            Duration::from_secs(
+                env::var(name)
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(default),
+            )
+        };
+        Ttls {
+            waiting: ttl("PONG_WAITING_TTL", 30 * 60),
+            idle: ttl("PONG_IDLE_TTL", 2 * 60),
+            finished: ttl("PONG_FINISHED_TTL", 10 * 60),
+        }
+    }
+}
+
+/// Whether `game` has been left long enough to clear out. `connected` is
+/// whether anyone has a websocket open to it.
+fn is_stale(game: &Game, connected: bool, ttls: &Ttls) -> bool {
+    let inactive = game.last_active.elapsed();
+    match game.status {
+        GameStatus::Waiting => game.competition.is_none() && inactive > ttls.waiting,
+        GameStatus::InProgress => !connected && inactive > ttls.idle,
+        GameStatus::Finished => inactive > ttls.finished,
+    }
+}
+
+#[instrument]
+pub async fn run(ttls: Ttls) {
+    let mut sweeps = interval(SWEEP_EVERY);
+    loop {
+        sweeps.tick().await;
+
+        let stale: Vec<String> = {
+            let games = GAME_STORE.read().await;
+            let connections = CONNECTION_STORE.read().await;
+            games
+                .values()
+                .filter(|game| is_stale(game, connections.contains_key(&game.id), &ttls))
+                .map(|game| game.id.clone())
+                .collect()
+        };
+
+        for game_id in stale {
+            if game::remove(&game_id, "This game was closed after being left").await {
+                info!(game_id, "cleared out stale game");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use pong_core::settings::MatchSettings;
+
+    use super::*;
+    use crate::game::{Competition, GameMode};
+
+    #[test]
+    fn games_are_kept_while_someone_might_come_back() {
+        let ttls = Ttls {
+            waiting: Duration::from_secs(60),
+            idle: Duration::from_secs(10),
+            finished: Duration::from_secs(30),
+        };
+        let mut game = Game::new("123".into(), GameMode::Singles, MatchSettings::default());
+        let ago = |secs| Instant::now() - Duration::from_secs(secs);
+
+        game.last_active = ago(20);
+        assert!(!is_stale(&game, false, &ttls));
+        game.status = GameStatus::InProgress;
+        assert!(is_stale(&game, false, &ttls));
+        assert!(!is_stale(&game, true, &ttls));
+        game.status = GameStatus::Finished;
+        assert!(!is_stale(&game, true, &ttls));
+
+        game.status = GameStatus::Waiting;
+        game.last_active = ago(90);
+        assert!(is_stale(&game, false, &ttls));
+        game.competition = Some(Competition::League("1".into()));
+        assert!(!is_stale(&game, false, &ttls));
+    }
+}
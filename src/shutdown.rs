@@ -0,0 +1,184 @@
+//! Stopping the server without cutting matches off mid-rally. On SIGTERM or
+//! Ctrl-C new games are refused and everyone connected is sent
+//! `server_shutdown`. Then, depending on `PONG_SHUTDOWN_WAIT`, the server waits
+//! for games in progress to finish their current `point` (the default), after
+//! which no new one is served, or the whole `match`, or doesn't wait at all
+//! with `none`, for at most `PONG_SHUTDOWN_DEADLINE` seconds. Games still in
+//! progress after that are written to `PONG_SHUTDOWN_FILE`, for
+//! `snapshot::restore` to pick back up when the server next starts. The Docker
+//! image keeps it in the `/var/lib/pong` volume so that it outlives the
+//! container.
+
+use std::{
+    collections::HashMap,
+    env,
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use pong_core::protocol::{ServerEvent, ServerShutdown};
+use tokio::{
+    signal::{
+        ctrl_c,
+        unix::{signal, SignalKind},
+    },
+    time::{sleep, Instant},
+};
+use tracing::{info, instrument};
+
+use crate::{
+    game::{self, Game, GameStatus},
+    snapshot, CONNECTION_STORE, GAME_STORE,
+};
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// New games aren't started once the server is on its way down.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wait {
+    None,
+    Point,
+    Match,
+}
+
+impl FromStr for Wait {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Wait::None),
+            "point" => Ok(Wait::Point),
+            "match" => Ok(Wait::Match),
+            _ => Err(format!("unknown shutdown wait: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub wait: Wait,
+    /// Docker gives containers ten seconds to stop by default.
+    pub deadline: Duration,
+    pub file: PathBuf,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            wait: env::var("PONG_SHUTDOWN_WAIT")
+                .ok()
+                .and_then(|w| w.parse().ok())
+                .unwrap_or(Wait::Point),
+            deadline: Duration::from_secs(
+                env::var("PONG_SHUTDOWN_DEADLINE")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(8),
+            ),
+            file: env::var("PONG_SHUTDOWN_FILE")
+                .unwrap_or_else(|_| String::from("in_progress.json"))
+                .into(),
+        }
+    }
+}
+
+/// Resolves once the server has been told to stop and has wound its games
+/// down, for the server to stop taking requests.
+pub async fn on_signal(config: Config) {
+    let mut terminate = signal(SignalKind::terminate()).expect("could not listen for SIGTERM");
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = ctrl_c() => {}
+    }
+    wind_down(config).await;
+}
+
+#[instrument]
+async fn wind_down(config: Config) {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    info!("shutting down");
+
+    let message = game::message(&ServerEvent::ServerShutdown(ServerShutdown {
+        text: String::from("The server is restarting"),
+        deadline_ms: config.deadline.as_millis() as u64,
+    }));
+    for players in CONNECTION_STORE.read().await.values() {
+        for tx in players.values() {
+            let _ = tx.send(message.clone());
+        }
+    }
+
+    // points played so far in each game in progress
+    let mut started = HashMap::new();
+    for game in GAME_STORE.write().await.values_mut() {
+        if game.status != GameStatus::InProgress {
+            continue;
+        }
+        if config.wait == Wait::Point {
+            game.hold_serve();
+        }
+        started.insert(game.id.clone(), game.stats.points);
+    }
+    let deadline = Instant::now() + config.deadline;
+    while !is_settled(config.wait, &*GAME_STORE.read().await, &started) {
+        if Instant::now() >= deadline {
+            info!("gave up waiting for games to finish");
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    snapshot::save(&config.file).await;
+}
+
+/// Whether the games that were in progress, with the points they had played
+/// in `started`, have got as far as `wait` asks.
+fn is_settled(wait: Wait, games: &HashMap<String, Game>, started: &HashMap<String, u32>) -> bool {
+    started.iter().all(|(id, points)| {
+        let Some(game) = games.get(id) else {
+            return true;
+        };
+        match wait {
+            Wait::None => true,
+            Wait::Point => game.status != GameStatus::InProgress || game.stats.points > *points,
+            Wait::Match => game.status != GameStatus::InProgress,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pong_core::settings::MatchSettings;
+
+    use super::*;
+    use crate::game::GameMode;
+
+    #[test]
+    fn waits_for_every_game_to_get_far_enough() {
+        let mut games = HashMap::new();
+        for id in ["a", "b"] {
+            let mut game = Game::new(id.into(), GameMode::Singles, MatchSettings::default());
+            game.status = GameStatus::InProgress;
+            game.stats.points = 2;
+            games.insert(id.to_string(), game);
+        }
+        let started = HashMap::from([("a".to_string(), 2), ("b".to_string(), 2)]);
+        assert!(is_settled(Wait::None, &games, &started));
+        assert!(!is_settled(Wait::Point, &games, &started));
+
+        games.get_mut("a").unwrap().stats.points = 3;
+        games.get_mut("b").unwrap().status = GameStatus::Finished;
+        assert!(is_settled(Wait::Point, &games, &started));
+
+        games.get_mut("a").unwrap().status = GameStatus::InProgress;
+        assert!(!is_settled(Wait::Match, &games, &started));
+        games.remove("a");
+        assert!(is_settled(Wait::Match, &games, &started));
+    }
+}
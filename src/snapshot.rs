@@ -0,0 +1,61 @@
+//! Games in progress saved on the way down and picked back up on startup, so
+//! a restart only pauses them. Restored games wait for their players to
+//! reconnect and ready up again before carrying on. Tournaments and leagues
+//! aren't saved, so their games come back as ordinary ones.
+
+use std::{fs, io, path::Path};
+
+use tracing::{error, info};
+
+use crate::{
+    game::{Game, GameSnapshot, GameStatus},
+    GAME_STORE,
+};
+
+/// Writes a snapshot of every game in progress to `file`.
+pub async fn save(file: &Path) {
+    let snapshots: Vec<GameSnapshot> = GAME_STORE
+        .read()
+        .await
+        .values()
+        .filter(|game| game.status == GameStatus::InProgress)
+        .map(Game::snapshot)
+        .collect();
+    if snapshots.is_empty() {
+        return;
+    }
+
+    match serde_json::to_string(&snapshots).map(|json| fs::write(file, json)) {
+        Ok(Ok(())) => info!(games = snapshots.len(), ?file, "saved games in progress"),
+        Ok(Err(err)) => error!("could not save games in progress: {}", err),
+        Err(err) => error!("could not save games in progress: {}", err),
+    }
+}
+
+/// Puts the games saved in `file`, if there is one, back in the store. The
+/// file is removed so they aren't restored a second time.
+pub async fn restore(file: &Path) {
+    let json = match fs::read_to_string(file) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+        Err(err) => return error!("could not read saved games: {}", err),
+    };
+    if let Err(err) = fs::remove_file(file) {
+        error!("could not remove saved games: {}", err);
+    }
+    let snapshots: Vec<GameSnapshot> = match serde_json::from_str(&json) {
+        Ok(snapshots) => snapshots,
+        Err(err) => return error!("could not read saved games: {}", err),
+    };
+
+    let mut games = GAME_STORE.write().await;
+    let restored = snapshots.len();
+    for snapshot in snapshots {
+        let mut game = Game::restore(snapshot);
+        // nothing to report the result to, and the reaper should treat it
+        // like any other game
+        game.competition = None;
+        games.insert(game.id.clone(), game);
+    }
+    info!(games = restored, ?file, "restored games in progress");
+}
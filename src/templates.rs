@@ -1,11 +1,13 @@
+use std::collections::HashMap;
+
 use askama::Template;
+use pong_core::{protocol::GameConfig, settings::MatchSettings};
 
 use crate::{
-    game::{PLAYER_1, PLAYER_2, SCALING_FACTOR},
-    pong::{
-        BALL_RADIUS, BALL_START, P1_START, P2_START, PLAYER_HEIGHT, PLAYER_WIDTH, PONG_HEIGHT,
-        PONG_WIDTH,
-    },
+    admin::GameSummary,
+    game::{Game, GameStatus, PLAYER_1, PLAYER_2},
+    league::{League, Standing},
+    tournament::{Bracket, Format, Seat, Tournament},
 };
 
 #[derive(Template)]
@@ -20,40 +22,276 @@ pub struct GameTemplate {
     ball_radius: f32,
     player_start_x: f32,
     player_start_y: f32,
-    opponent_start_x: f32,
-    opponent_start_y: f32,
+    horizontal: bool,
+    lane_min: f32,
+    lane_max: f32,
+    paddle_starts: String,
     ball_start_x: f32,
     ball_start_y: f32,
+    scaling_factor: f32,
+    map: String,
+    /// Pixels per second, zero if the player's paddle isn't slowed down.
+    max_speed: f32,
+    /// Every handicapped player's id and a description of their handicap.
+    handicaps: Vec<(String, String)>,
 }
 
 impl GameTemplate {
-    pub fn new(game_id: String, player_id: String) -> Self {
-        let player = match player_id.as_str() {
-            PLAYER_1 => (P1_START.0 * SCALING_FACTOR, P1_START.1 * SCALING_FACTOR),
-            PLAYER_2 => (P2_START.0 * SCALING_FACTOR, P2_START.1 * SCALING_FACTOR),
-            _ => panic!("no such player"),
-        };
-
-        let opponent = match player_id.as_str() {
-            PLAYER_1 => (P2_START.0 * SCALING_FACTOR, P2_START.1 * SCALING_FACTOR),
-            PLAYER_2 => (P1_START.0 * SCALING_FACTOR, P1_START.1 * SCALING_FACTOR),
-            _ => panic!("no such player"),
-        };
+    pub fn new(config: &GameConfig, settings: &MatchSettings) -> Self {
+        let own = config.own_paddle();
+        let mut handicaps: Vec<_> = settings
+            .handicaps
+            .iter()
+            .map(|(id, handicap)| (id.clone(), handicap.to_string()))
+            .collect();
+        handicaps.sort();
 
         GameTemplate {
-            game_id,
-            player_id,
-            game_width: PONG_WIDTH * SCALING_FACTOR,
-            game_height: PONG_HEIGHT * SCALING_FACTOR,
-            player_width: PLAYER_WIDTH * SCALING_FACTOR,
-            player_height: PLAYER_HEIGHT * SCALING_FACTOR,
-            ball_radius: BALL_RADIUS * SCALING_FACTOR,
-            player_start_x: player.0,
-            player_start_y: player.1,
-            opponent_start_x: opponent.0,
-            opponent_start_y: opponent.1,
-            ball_start_x: BALL_START.0 * SCALING_FACTOR,
-            ball_start_y: BALL_START.1 * SCALING_FACTOR,
+            game_id: config.game_id.clone(),
+            player_id: config.player_id.clone(),
+            game_width: config.width,
+            game_height: config.height,
+            player_width: config.paddle_width,
+            player_height: own.length,
+            ball_radius: config.ball_radius,
+            player_start_x: own.x,
+            player_start_y: own.y,
+            horizontal: own.horizontal,
+            lane_min: config.lane_min,
+            lane_max: config.lane_max,
+            paddle_starts: serde_json::to_string(&config.paddles).unwrap(),
+            ball_start_x: config.ball_start.x,
+            ball_start_y: config.ball_start.y,
+            scaling_factor: config.scaling_factor,
+            map: serde_json::to_string(&config.map).unwrap(),
+            max_speed: config.max_speed.unwrap_or(0.0),
+            handicaps,
+        }
+    }
+}
+
+pub struct MatchView {
+    players: Vec<(String, Option<String>)>,
+    status: String,
+}
+
+pub struct RoundView {
+    title: String,
+    matches: Vec<MatchView>,
+}
+
+#[derive(Template)]
+#[template(path = "tournament.html")]
+pub struct TournamentTemplate {
+    name: String,
+    format: String,
+    champion: Option<String>,
+    rounds: Vec<RoundView>,
+}
+
+impl TournamentTemplate {
+    pub fn new(tournament: &Tournament, games: &HashMap<String, Game>) -> Self {
+        let last_winners_round = tournament
+            .matches
+            .iter()
+            .filter(|m| m.bracket == Bracket::Winners)
+            .map(|m| m.round)
+            .max();
+
+        let mut rounds: Vec<RoundView> = vec![];
+        let mut current = None;
+        for (i, bracket_match) in tournament.matches.iter().enumerate() {
+            if current != Some((bracket_match.bracket, bracket_match.round)) {
+                current = Some((bracket_match.bracket, bracket_match.round));
+                let title = match (tournament.format, bracket_match.bracket) {
+                    (Format::SingleElimination, _)
+                        if Some(bracket_match.round) == last_winners_round =>
+                    {
+                        String::from("Final")
+                    }
+                    (Format::SingleElimination, _) => format!("Round {}", bracket_match.round),
+                    (_, Bracket::Winners) => format!("Winners round {}", bracket_match.round),
+                    (_, Bracket::Losers) => format!("Losers round {}", bracket_match.round),
+                    (_, Bracket::GrandFinal) => String::from("Grand final"),
+                };
+                rounds.push(RoundView {
+                    title,
+                    matches: vec![],
+                });
+            }
+
+            let seats = tournament.seats(i);
+            let game = bracket_match.game_id.as_ref().and_then(|id| games.get(id));
+            let status = match (bracket_match.result, game) {
+                (Some((winner, Seat::Bye)), _) => {
+                    format!("{} goes through", tournament.seat_name(Some(winner)))
+                }
+                (Some((winner, _)), _) => format!("{} won", tournament.seat_name(Some(winner))),
+                (None, Some(game)) => game_status(game),
+                (None, None) => String::from("waiting for earlier matches"),
+            };
+            let players = join_links(
+                seats.map(|seat| tournament.seat_name(seat)),
+                game.filter(|_| bracket_match.result.is_none()),
+            );
+
+            if let Some(round) = rounds.last_mut() {
+                round.matches.push(MatchView { players, status });
+            }
+        }
+
+        TournamentTemplate {
+            name: tournament.name.clone(),
+            format: tournament.format.name().to_string(),
+            champion: tournament.champion().map(String::from),
+            rounds,
+        }
+    }
+}
+
+/// Each player's name and, while their seat in `game` is free, the link to
+/// join as them.
+fn join_links(names: [&str; 2], game: Option<&Game>) -> Vec<(String, Option<String>)> {
+    names
+        .iter()
+        .zip([PLAYER_1, PLAYER_2])
+        .map(|(name, player_id)| {
+            let link = game
+                .filter(|g| g.is_slot_free(player_id))
+                .map(|g| format!("/games/{}?player={}", g.id, player_id));
+            (name.to_string(), link)
+        })
+        .collect()
+}
+
+pub fn game_status(game: &Game) -> String {
+    match game.status {
+        GameStatus::Waiting => String::from("waiting for players"),
+        GameStatus::InProgress => String::from("in progress"),
+        GameStatus::Finished => String::from("finished"),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "league.html")]
+pub struct LeagueTemplate {
+    id: String,
+    name: String,
+    standings: Vec<Standing>,
+    rounds: Vec<RoundView>,
+}
+
+impl LeagueTemplate {
+    pub fn new(league: &League, games: &HashMap<String, Game>) -> Self {
+        let mut rounds: Vec<RoundView> = vec![];
+        for fixture in &league.fixtures {
+            if rounds.len() < fixture.round {
+                rounds.push(RoundView {
+                    title: format!("Round {}", fixture.round),
+                    matches: vec![],
+                });
+            }
+
+            let names = fixture.players.map(|p| league.players[p].as_str());
+            let game = fixture.game_id.as_ref().and_then(|id| games.get(id));
+            let status = match (fixture.result, game) {
+                (Some([first, second]), _) => format!("{} - {}", first, second),
+                (None, Some(game)) => game_status(game),
+                (None, None) => String::new(),
+            };
+            let players = join_links(names, game.filter(|_| fixture.result.is_none()));
+
+            if let Some(round) = rounds.last_mut() {
+                round.matches.push(MatchView { players, status });
+            }
+        }
+
+        LeagueTemplate {
+            id: league.id.clone(),
+            name: league.name.clone(),
+            standings: league.standings(),
+            rounds,
+        }
+    }
+}
+
+pub struct PlayerStatsView {
+    player: String,
+    standing: String,
+    hits: u32,
+    points_on_serve: u32,
+    /// Pixels.
+    paddle_distance: String,
+}
+
+#[derive(Template)]
+#[template(path = "results.html")]
+pub struct ResultsTemplate {
+    status: String,
+    winners: Vec<String>,
+    players: Vec<PlayerStatsView>,
+    longest_rally: u32,
+    average_rally: String,
+    /// Pixels per second.
+    fastest_ball: String,
+    time_in_play: String,
+}
+
+impl ResultsTemplate {
+    pub fn new(game: &Game) -> Self {
+        let stats = &game.stats;
+        let scale = game.settings.scaling_factor;
+        let players = game
+            .mode
+            .slots()
+            .iter()
+            .filter(|(id, _, _)| !game.is_slot_free(id))
+            .map(|(id, _, _)| {
+                let player = game.get_player(id);
+                PlayerStatsView {
+                    player: id.to_string(),
+                    standing: match player.lives {
+                        Some(lives) => format!("{} lives", lives),
+                        None => player.score.to_string(),
+                    },
+                    hits: stats.hits.get(*id).copied().unwrap_or_default(),
+                    points_on_serve: stats.points_on_serve.get(*id).copied().unwrap_or_default(),
+                    paddle_distance: format!(
+                        "{:.0}",
+                        stats.paddle_distance.get(*id).copied().unwrap_or_default() * scale
+                    ),
+                }
+            })
+            .collect();
+        let seconds = stats.time_in_play.as_secs();
+
+        ResultsTemplate {
+            status: game_status(game),
+            winners: game.winners().into_iter().map(String::from).collect(),
+            players,
+            longest_rally: stats.longest_rally,
+            average_rally: format!("{:.1}", stats.average_rally()),
+            fastest_ball: format!("{:.0}", stats.fastest_ball * scale),
+            time_in_play: format!("{}:{:02}", seconds / 60, seconds % 60),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin.html")]
+pub struct AdminTemplate {
+    token: String,
+    games: Vec<GameSummary>,
+    /// What came of the last thing the admin did, if it needs saying.
+    notice: Option<&'static str>,
+}
+
+impl AdminTemplate {
+    pub fn new(token: &str, games: Vec<GameSummary>, notice: Option<&'static str>) -> Self {
+        AdminTemplate {
+            token: token.to_string(),
+            games,
+            notice,
         }
     }
 }
@@ -0,0 +1,459 @@
+use std::{collections::HashMap, str::FromStr};
+
+use pong_core::settings::MatchSettings;
+use uuid::Uuid;
+
+use crate::{
+    game::{Competition, Game, GameMode, PLAYER_1, PLAYER_2},
+    GAME_STORE, TOURNAMENT_STORE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    SingleElimination,
+    /// Losing once drops a player into the losers bracket, losing twice
+    /// knocks them out. The grand final is a single game, there is no
+    /// bracket reset.
+    DoubleElimination,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::SingleElimination => "Single elimination",
+            Format::DoubleElimination => "Double elimination",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single_elimination" => Ok(Format::SingleElimination),
+            "double_elimination" => Ok(Format::DoubleElimination),
+            _ => Err(format!("unknown tournament format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bracket {
+    Winners,
+    Losers,
+    GrandFinal,
+}
+
+/// Where a match gets one of its two players from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Entrant(usize),
+    Bye,
+    Winner(usize),
+    Loser(usize),
+}
+
+/// Who ends up in a seat once its source has been decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seat {
+    Entrant(usize),
+    Bye,
+}
+
+#[derive(Debug, Clone)]
+pub struct BracketMatch {
+    pub bracket: Bracket,
+    pub round: usize,
+    sources: [Source; 2],
+    /// The game the two players are sent to, seat 0 plays as `PLAYER_1`.
+    pub game_id: Option<String>,
+    /// Winner and loser once the match has been played or given as a walkover.
+    pub result: Option<(Seat, Seat)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    pub id: String,
+    pub name: String,
+    pub format: Format,
+    pub entrants: Vec<String>,
+    /// Used for every game in the tournament.
+    pub settings: MatchSettings,
+    pub matches: Vec<BracketMatch>,
+}
+
+impl Tournament {
+    /// Draws up the whole bracket for `entrants`, seeded in the order given.
+    /// Fields that aren't a power of two are filled with byes for the top seeds.
+    pub fn new(
+        id: String,
+        name: String,
+        format: Format,
+        entrants: Vec<String>,
+        settings: MatchSettings,
+    ) -> Result<Self, String> {
+        if entrants.len() < 2 {
+            return Err(String::from("a tournament needs at least two entrants"));
+        }
+
+        let mut matches = vec![];
+        let seat = |seed: usize| {
+            if seed < entrants.len() {
+                Source::Entrant(seed)
+            } else {
+                Source::Bye
+            }
+        };
+
+        let mut winners_rounds = vec![seed_order(entrants.len().next_power_of_two())
+            .chunks(2)
+            .map(|pair| {
+                add_match(
+                    &mut matches,
+                    Bracket::Winners,
+                    1,
+                    [seat(pair[0]), seat(pair[1])],
+                )
+            })
+            .collect::<Vec<_>>()];
+        while let Some(previous) = winners_rounds.last().filter(|r| r.len() > 1) {
+            let round = winners_rounds.len() + 1;
+            let next = previous
+                .chunks(2)
+                .map(|pair| {
+                    add_match(
+                        &mut matches,
+                        Bracket::Winners,
+                        round,
+                        [Source::Winner(pair[0]), Source::Winner(pair[1])],
+                    )
+                })
+                .collect();
+            winners_rounds.push(next);
+        }
+
+        if format == Format::DoubleElimination {
+            // losers of each winners round drop down to meet the survivors of
+            // the losers bracket, who first play each other down to the same number
+            let mut survivors: Vec<Source> = winners_rounds[0]
+                .iter()
+                .map(|m| Source::Loser(*m))
+                .collect();
+            let mut round = 0;
+            for dropping in &winners_rounds[1..] {
+                round += 1;
+                survivors = survivors
+                    .chunks(2)
+                    .map(|pair| {
+                        Source::Winner(add_match(
+                            &mut matches,
+                            Bracket::Losers,
+                            round,
+                            [pair[0], pair[1]],
+                        ))
+                    })
+                    .collect();
+
+                round += 1;
+                survivors = survivors
+                    .iter()
+                    .zip(dropping.iter().rev())
+                    .map(|(survivor, dropped)| {
+                        Source::Winner(add_match(
+                            &mut matches,
+                            Bracket::Losers,
+                            round,
+                            [*survivor, Source::Loser(*dropped)],
+                        ))
+                    })
+                    .collect();
+            }
+
+            let winners_final = winners_rounds[winners_rounds.len() - 1][0];
+            add_match(
+                &mut matches,
+                Bracket::GrandFinal,
+                1,
+                [Source::Winner(winners_final), survivors[0]],
+            );
+        }
+
+        let mut tournament = Tournament {
+            id,
+            name,
+            format,
+            entrants,
+            settings,
+            matches,
+        };
+        tournament.advance();
+        Ok(tournament)
+    }
+
+    fn resolve(&self, source: Source) -> Option<Seat> {
+        match source {
+            Source::Entrant(entrant) => Some(Seat::Entrant(entrant)),
+            Source::Bye => Some(Seat::Bye),
+            Source::Winner(m) => self.matches[m].result.map(|(winner, _)| winner),
+            Source::Loser(m) => self.matches[m].result.map(|(_, loser)| loser),
+        }
+    }
+
+    /// Who is playing in match `m`, `None` for seats still waiting on an
+    /// earlier match.
+    pub fn seats(&self, m: usize) -> [Option<Seat>; 2] {
+        self.matches[m].sources.map(|source| self.resolve(source))
+    }
+
+    pub fn seat_name(&self, seat: Option<Seat>) -> &str {
+        match seat {
+            Some(Seat::Entrant(entrant)) => &self.entrants[entrant],
+            Some(Seat::Bye) => "bye",
+            None => "TBD",
+        }
+    }
+
+    /// Gives walkovers to anyone drawn against a bye and returns the matches
+    /// that now have both players but no game yet.
+    pub fn advance(&mut self) -> Vec<usize> {
+        loop {
+            let mut changed = false;
+            for m in 0..self.matches.len() {
+                if self.matches[m].result.is_some() {
+                    continue;
+                }
+                if let [Some(Seat::Bye), Some(other)] | [Some(other), Some(Seat::Bye)] =
+                    self.seats(m)
+                {
+                    self.matches[m].result = Some((other, Seat::Bye));
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        (0..self.matches.len())
+            .filter(|m| {
+                self.matches[*m].result.is_none()
+                    && self.matches[*m].game_id.is_none()
+                    && self.seats(*m).iter().all(Option::is_some)
+            })
+            .collect()
+    }
+
+    /// Records the result of the match played in `game_id`, `winner` being
+    /// the winning seat. Returns false if no match is waiting on that game.
+    pub fn record_result(&mut self, game_id: &str, winner: usize) -> bool {
+        let Some(m) = self
+            .matches
+            .iter()
+            .position(|m| m.result.is_none() && m.game_id.as_deref() == Some(game_id))
+        else {
+            return false;
+        };
+        if let [Some(first), Some(second)] = self.seats(m) {
+            self.matches[m].result = Some(if winner == 0 {
+                (first, second)
+            } else {
+                (second, first)
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Forgets the game for the undecided match played in `game_id`, so that
+    /// `advance` offers the match again. Returns false if no match is waiting
+    /// on that game.
+    pub fn unschedule(&mut self, game_id: &str) -> bool {
+        match self
+            .matches
+            .iter_mut()
+            .find(|m| m.result.is_none() && m.game_id.as_deref() == Some(game_id))
+        {
+            Some(m) => {
+                m.game_id = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The winner of the final once it has been played.
+    pub fn champion(&self) -> Option<&str> {
+        match self.matches.last()?.result? {
+            (Seat::Entrant(entrant), _) => Some(&self.entrants[entrant]),
+            (Seat::Bye, _) => None,
+        }
+    }
+}
+
+fn add_match(
+    matches: &mut Vec<BracketMatch>,
+    bracket: Bracket,
+    round: usize,
+    sources: [Source; 2],
+) -> usize {
+    matches.push(BracketMatch {
+        bracket,
+        round,
+        sources,
+        game_id: None,
+        result: None,
+    });
+    matches.len() - 1
+}
+
+/// Seeds in bracket order for a field of `size`, a power of two, so that the
+/// top seeds can only meet in the later rounds. Neighbouring pairs play each
+/// other in the first round.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let seeds = order.len() * 2;
+        order = order.iter().flat_map(|s| [*s, seeds - 1 - s]).collect();
+    }
+    order
+}
+
+/// Creates a game for every match that is ready to be played.
+pub fn schedule(tournament: &mut Tournament, games: &mut HashMap<String, Game>) {
+    for m in tournament.advance() {
+        let game_id = Uuid::new_v4().to_string();
+        let mut game = Game::new(
+            game_id.clone(),
+            GameMode::Singles,
+            tournament.settings.clone(),
+        );
+        game.competition = Some(Competition::Tournament(tournament.id.clone()));
+        games.insert(game_id.clone(), game);
+        tournament.matches[m].game_id = Some(game_id);
+    }
+}
+
+/// Moves the winners of a finished tournament game on through the bracket.
+pub async fn record_result(tournament_id: String, game_id: String, winners: Vec<String>) {
+    let winner = if winners.iter().any(|w| w == PLAYER_1) {
+        0
+    } else if winners.iter().any(|w| w == PLAYER_2) {
+        1
+    } else {
+        return;
+    };
+
+    let mut tournaments_write = TOURNAMENT_STORE.write().await;
+    if let Some(tournament) = tournaments_write.get_mut(&tournament_id) {
+        if tournament.record_result(&game_id, winner) {
+            schedule(tournament, &mut *GAME_STORE.write().await);
+        }
+    }
+}
+
+/// Gives the match played in `game_id`, which was taken away before it
+/// finished, a new game.
+pub async fn replay(tournament_id: String, game_id: String) {
+    let mut tournaments_write = TOURNAMENT_STORE.write().await;
+    if let Some(tournament) = tournaments_write.get_mut(&tournament_id) {
+        if tournament.unschedule(&game_id) {
+            schedule(tournament, &mut *GAME_STORE.write().await);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entrants(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("player {}", i)).collect()
+    }
+
+    fn play(tournament: &mut Tournament, m: usize, winner: usize) {
+        let game_id = format!("game {}", m);
+        tournament.matches[m].game_id = Some(game_id.clone());
+        assert!(tournament.record_result(&game_id, winner));
+    }
+
+    #[test]
+    fn single_elimination_gives_top_seed_a_bye() {
+        let mut tournament = Tournament::new(
+            "t".into(),
+            "cup".into(),
+            Format::SingleElimination,
+            entrants(3),
+            MatchSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(tournament.matches.len(), 3);
+        assert_eq!(
+            tournament.matches[0].result,
+            Some((Seat::Entrant(0), Seat::Bye))
+        );
+        assert_eq!(tournament.advance(), vec![1]);
+
+        play(&mut tournament, 1, 1);
+        assert_eq!(tournament.advance(), vec![2]);
+        assert_eq!(
+            tournament.seats(2),
+            [Some(Seat::Entrant(0)), Some(Seat::Entrant(2))]
+        );
+
+        play(&mut tournament, 2, 1);
+        assert_eq!(tournament.champion(), Some("player 2"));
+    }
+
+    #[test]
+    fn unscheduled_matches_are_offered_again() {
+        let mut tournament = Tournament::new(
+            "t".into(),
+            "cup".into(),
+            Format::SingleElimination,
+            entrants(2),
+            MatchSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(tournament.advance(), vec![0]);
+        tournament.matches[0].game_id = Some("game".into());
+        assert!(tournament.advance().is_empty());
+
+        assert!(!tournament.unschedule("another game"));
+        assert!(tournament.unschedule("game"));
+        assert_eq!(tournament.advance(), vec![0]);
+    }
+
+    #[test]
+    fn double_elimination_sends_losers_to_the_grand_final() {
+        let mut tournament = Tournament::new(
+            "t".into(),
+            "cup".into(),
+            Format::DoubleElimination,
+            entrants(4),
+            MatchSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(tournament.matches.len(), 6);
+        assert_eq!(tournament.advance(), vec![0, 1]);
+
+        // the top seed wins the winners bracket
+        play(&mut tournament, 0, 0);
+        play(&mut tournament, 1, 0);
+        play(&mut tournament, 2, 0);
+        assert_eq!(tournament.advance(), vec![3]);
+
+        // and loses the grand final to whoever comes through the losers bracket
+        play(&mut tournament, 3, 0);
+        play(&mut tournament, 4, 1);
+        assert_eq!(
+            tournament.seats(5),
+            [Some(Seat::Entrant(0)), Some(Seat::Entrant(1))]
+        );
+        play(&mut tournament, 5, 1);
+        assert_eq!(tournament.champion(), Some("player 1"));
+    }
+}